@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hands::Hands,
+    inventory::{EquipSlot, Inventory},
+};
+
+/// Carried in a hand or worn in the id slot, holds the access levels that doors and restricted machines check
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IdCard {
+    pub access: Vec<String>,
+}
+
+/// Only someone carrying an id with this access can operate the machine
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct RequiresAccess(pub String);
+
+/// Every access level on the cards someone is holding or wearing, `card` looks up whatever
+/// they carry so this works from queries and from the world alike
+pub fn carried_access<'a>(
+    hands: Option<&Hands>,
+    inventory: Option<&Inventory>,
+    card: impl Fn(Entity) -> Option<&'a IdCard>,
+) -> Vec<String> {
+    hands
+        .into_iter()
+        .flat_map(|hands| hands.held())
+        .chain(inventory.and_then(|inventory| inventory.get(EquipSlot::Id)))
+        .filter_map(card)
+        .flat_map(|card| card.access.iter().cloned())
+        .collect()
+}
+
+pub fn has_access(carried: &[String], needed: &str) -> bool {
+    carried.iter().any(|access| access == needed)
+}
@@ -0,0 +1,234 @@
+use bevy::prelude::*;
+use bevy_turborand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access::{carried_access, has_access, IdCard, RequiresAccess},
+    graphics::Impassable,
+    grid::{neumann_neighbors, ConnectedComponents, Grid, GridLocation},
+    hands::{GiveItem, Hands},
+    health::Incapacitated,
+    interactable::VendingMachine,
+    inventory::Inventory,
+    path::PathFollower,
+    power::PowerConsumer,
+    turn::TurnScheduler,
+    wiring::Wiring,
+    Npc,
+};
+
+/// What an npc is trying to do this turn
+#[derive(Component, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Behavior {
+    #[default]
+    Idle,
+    /// Roam between random points in the region the npc is standing in
+    Wander,
+    GoTo(GridLocation),
+    Flee(Entity),
+    Follow(Entity),
+    /// Walk up to an `Interactable` and use it
+    Use(Entity),
+}
+
+/// Cycles an npc through behaviors, each held for a number of turns
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Routine {
+    pub steps: Vec<(Behavior, u32)>,
+    current: usize,
+    turns_left: u32,
+}
+
+impl Routine {
+    pub fn new(steps: Vec<(Behavior, u32)>) -> Self {
+        let turns_left = steps.first().map(|(_, turns)| *turns).unwrap_or(0);
+        Self {
+            steps,
+            current: 0,
+            turns_left,
+        }
+    }
+
+    pub fn current_behavior(&self) -> Behavior {
+        self.steps
+            .get(self.current)
+            .map(|(behavior, _)| behavior.clone())
+            .unwrap_or_default()
+    }
+}
+
+pub fn advance_routines(
+    mut npcs: Query<(Entity, &mut Routine, &mut Behavior), Without<Incapacitated>>,
+    scheduler: Res<TurnScheduler>,
+) {
+    for (entity, mut routine, mut behavior) in &mut npcs {
+        if routine.steps.is_empty() || !scheduler.is_acting(entity) {
+            continue;
+        }
+        if routine.turns_left > 0 {
+            routine.turns_left -= 1;
+            continue;
+        }
+        routine.current = (routine.current + 1) % routine.steps.len();
+        routine.turns_left = routine.steps[routine.current].1;
+        *behavior = routine.current_behavior();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn npc_behavior(
+    mut commands: Commands,
+    mut npcs: Query<
+        (
+            Entity,
+            &GridLocation,
+            &mut Behavior,
+            &mut RngComponent,
+            Option<&mut PathFollower>,
+        ),
+        (With<Npc>, Without<Incapacitated>),
+    >,
+    targets: Query<&GridLocation>,
+    hands: Query<&Hands>,
+    inventories: Query<&Inventory>,
+    cards: Query<&IdCard>,
+    grid: Res<Grid<Impassable>>,
+    connected: Res<ConnectedComponents<Impassable>>,
+    mut machines: Query<(
+        &mut VendingMachine,
+        Option<&Wiring>,
+        Option<&PowerConsumer>,
+        Option<&RequiresAccess>,
+    )>,
+    mut give_item: EventWriter<GiveItem>,
+    scheduler: Res<TurnScheduler>,
+) {
+    for (entity, location, mut behavior, mut rng, follower) in &mut npcs {
+        if !scheduler.is_acting(entity) {
+            continue;
+        }
+        let destination = match behavior.clone() {
+            Behavior::Idle => None,
+            Behavior::Wander => match follower.as_ref() {
+                Some(follower) => Some(follower.destination.clone()),
+                None => random_point_in_region(&grid, &connected, location, &mut rng),
+            },
+            Behavior::GoTo(target) => {
+                if *location == target {
+                    *behavior = Behavior::Idle;
+                    None
+                } else {
+                    Some(target)
+                }
+            }
+            Behavior::Flee(threat) => targets
+                .get(threat)
+                .ok()
+                .and_then(|threat| flee_step(&grid, location, threat)),
+            Behavior::Follow(leader) => targets.get(leader).ok().cloned(),
+            Behavior::Use(target) => {
+                let Ok(target_location) = targets.get(target) else {
+                    *behavior = Behavior::Idle;
+                    continue;
+                };
+                let distance = (target_location.get_location() - location.get_location()).abs();
+                if distance.x + distance.y <= 1 {
+                    if hands.get(entity).is_ok_and(Hands::can_pickup) {
+                        use_interactable(
+                            entity,
+                            target,
+                            &carried_access(
+                                hands.get(entity).ok(),
+                                inventories.get(entity).ok(),
+                                |held| cards.get(held).ok(),
+                            ),
+                            &mut machines,
+                            &mut give_item,
+                            scheduler.clock,
+                        );
+                    }
+                    *behavior = Behavior::Idle;
+                    None
+                } else {
+                    Some(target_location.clone())
+                }
+            }
+        };
+
+        match (destination, follower) {
+            (Some(destination), Some(mut follower)) => follower.set_destination(destination),
+            (Some(destination), None) => {
+                commands
+                    .entity(entity)
+                    .insert(PathFollower::new(destination));
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<PathFollower>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+// Npcs stand on impassable tiles so look at the regions next to them
+fn random_point_in_region(
+    grid: &Grid<Impassable>,
+    connected: &ConnectedComponents<Impassable>,
+    location: &GridLocation,
+    rng: &mut RngComponent,
+) -> Option<GridLocation> {
+    let mut rng = RandBorrowed::from(rng.get_mut());
+    neumann_neighbors(grid, location)
+        .iter()
+        .find_map(|open| connected.get_random_point_in_same_component(open, &mut rng))
+}
+
+fn flee_step(
+    grid: &Grid<Impassable>,
+    location: &GridLocation,
+    threat: &GridLocation,
+) -> Option<GridLocation> {
+    let distance = |point: &GridLocation| {
+        let offset = point.get_location() - threat.get_location();
+        offset.x * offset.x + offset.y * offset.y
+    };
+    neumann_neighbors(grid, location)
+        .into_iter()
+        .filter(|step| distance(step) > distance(location))
+        .max_by_key(distance)
+}
+
+fn use_interactable(
+    npc: Entity,
+    target: Entity,
+    access: &[String],
+    machines: &mut Query<(
+        &mut VendingMachine,
+        Option<&Wiring>,
+        Option<&PowerConsumer>,
+        Option<&RequiresAccess>,
+    )>,
+    give_item: &mut EventWriter<GiveItem>,
+    clock: u64,
+) {
+    if let Ok((mut machine, wiring, power, required)) = machines.get_mut(target) {
+        if power.is_some_and(|power| !power.powered) {
+            return;
+        }
+        if required.is_some_and(|required| !has_access(access, &required.0)) {
+            return;
+        }
+        // NPCs don't know how to get anything out of a tampered machine
+        if wiring.is_some_and(|wiring| !wiring.can_dispense(clock) || wiring.locked(clock)) {
+            return;
+        }
+        if !machine.options.is_empty() {
+            let item = machine.options.remove(0);
+            machine.selection = 0;
+            give_item.send(GiveItem {
+                receiver: Some(npc),
+                item,
+            });
+        }
+    }
+}
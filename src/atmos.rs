@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::Impassable,
+    grid::{DirtyGridEvent, Grid, GridLocation},
+    health::{Damage, DamageKind, Health, Vitality},
+    turn::{TurnScheduler, MOVE_COST},
+    Floor,
+};
+
+// Ideal gas law in kPa, moles, litres and kelvin
+const GAS_CONSTANT: f32 = 8.314;
+pub const TILE_VOLUME: f32 = 2500.0;
+pub const ROOM_TEMPERATURE: f32 = 293.15;
+pub const SPACE_TEMPERATURE: f32 = 2.7;
+pub const ONE_ATMOSPHERE: f32 = 101.325;
+// Share of the difference that moves between two neighbors each turn, above 0.25 it oscillates
+const DIFFUSION_RATE: f32 = 0.2;
+
+const SAFE_OXYGEN_PRESSURE: f32 = 16.0;
+const SAFE_PLASMA_PRESSURE: f32 = 0.5;
+const SAFE_TEMPERATURE: std::ops::Range<f32> = 260.0..360.0;
+const SUFFOCATION_DAMAGE: u32 = 2;
+const OXYGEN_RECOVERY: u32 = 1;
+const TOXIN_DAMAGE: u32 = 2;
+const TEMPERATURE_DAMAGE: u32 = 2;
+// Moles of oxygen turned into carbon dioxide by each breath
+const BREATH: f32 = 0.05;
+
+/// Gas in one tile, amounts in moles
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GasMix {
+    pub oxygen: f32,
+    pub nitrogen: f32,
+    pub carbon_dioxide: f32,
+    pub plasma: f32,
+    pub temperature: f32,
+}
+
+impl Default for GasMix {
+    fn default() -> Self {
+        Self::vacuum()
+    }
+}
+
+impl GasMix {
+    pub fn vacuum() -> Self {
+        Self {
+            oxygen: 0.0,
+            nitrogen: 0.0,
+            carbon_dioxide: 0.0,
+            plasma: 0.0,
+            temperature: SPACE_TEMPERATURE,
+        }
+    }
+
+    /// One atmosphere of breathable air at room temperature
+    pub fn station_air() -> Self {
+        let total = ONE_ATMOSPHERE * TILE_VOLUME / (GAS_CONSTANT * ROOM_TEMPERATURE);
+        Self {
+            oxygen: total * 0.21,
+            nitrogen: total * 0.79,
+            carbon_dioxide: 0.0,
+            plasma: 0.0,
+            temperature: ROOM_TEMPERATURE,
+        }
+    }
+
+    pub fn total(&self) -> f32 {
+        self.oxygen + self.nitrogen + self.carbon_dioxide + self.plasma
+    }
+
+    pub fn partial_pressure(&self, moles: f32) -> f32 {
+        moles * GAS_CONSTANT * self.temperature / TILE_VOLUME
+    }
+
+    pub fn pressure(&self) -> f32 {
+        self.partial_pressure(self.total())
+    }
+
+    // Moles of each gas then heat, the quantities that actually flow between tiles
+    fn to_flows(self) -> [f32; 5] {
+        [
+            self.oxygen,
+            self.nitrogen,
+            self.carbon_dioxide,
+            self.plasma,
+            self.total() * self.temperature,
+        ]
+    }
+
+    fn from_flows(flows: [f32; 5]) -> Self {
+        let [oxygen, nitrogen, carbon_dioxide, plasma, heat] = flows.map(|flow| flow.max(0.0));
+        let mut mix = Self {
+            oxygen,
+            nitrogen,
+            carbon_dioxide,
+            plasma,
+            temperature: SPACE_TEMPERATURE,
+        };
+        let total = mix.total();
+        if total > f32::EPSILON {
+            mix.temperature = heat / total;
+        }
+        mix
+    }
+}
+
+/// Gas in every tile, laid out like `Grid`, stepped once per turn
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct Atmosphere {
+    tiles: Vec<GasMix>,
+    size: UVec2,
+    next_update: u64,
+}
+
+impl Atmosphere {
+    fn index(&self, location: &GridLocation) -> Option<usize> {
+        (location.x >= 0
+            && location.y >= 0
+            && location.x < self.size.x as i32
+            && location.y < self.size.y as i32)
+            .then(|| location.x as usize * self.size.y as usize + location.y as usize)
+    }
+
+    /// Vacuum outside the map
+    pub fn get(&self, location: &GridLocation) -> GasMix {
+        self.index(location)
+            .map(|index| self.tiles[index])
+            .unwrap_or_default()
+    }
+
+    pub fn get_mut(&mut self, location: &GridLocation) -> Option<&mut GasMix> {
+        self.index(location).map(|index| &mut self.tiles[index])
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    // Floor starts out full of air and everything else is space
+    fn reset(&mut self, floor: &Grid<Floor>) {
+        self.size = floor.size();
+        self.tiles = floor
+            .all_points()
+            .iter()
+            .map(|location| {
+                if floor.occupied(location) {
+                    GasMix::station_air()
+                } else {
+                    GasMix::vacuum()
+                }
+            })
+            .collect();
+    }
+
+    /// Spreads gas between open neighbors, tiles off the map and in `space` are emptied
+    fn step(&mut self, sealed: &[bool], space: &[bool]) {
+        let height = self.size.y as i32;
+        let old = self
+            .tiles
+            .iter()
+            .map(|mix| mix.to_flows())
+            .collect::<Vec<_>>();
+        for x in 0..self.size.x as i32 {
+            for y in 0..height {
+                let index = (x * height + y) as usize;
+                if sealed[index] {
+                    continue;
+                }
+                let mut flows = old[index];
+                for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                    let neighbor = self.index(&GridLocation::from(IVec2::new(x, y) + offset));
+                    // The edge of the map opens onto space
+                    let other = match neighbor {
+                        Some(neighbor) if sealed[neighbor] => continue,
+                        Some(neighbor) => old[neighbor],
+                        None => GasMix::vacuum().to_flows(),
+                    };
+                    for (flow, (theirs, ours)) in
+                        flows.iter_mut().zip(other.iter().zip(old[index].iter()))
+                    {
+                        *flow += (theirs - ours) * DIFFUSION_RATE;
+                    }
+                }
+                self.tiles[index] = if space[index] {
+                    GasMix::vacuum()
+                } else {
+                    GasMix::from_flows(flows)
+                };
+            }
+        }
+    }
+}
+
+/// A hole in the hull, the tile it sits on is open to space
+#[derive(Component, Clone, Copy, Default)]
+pub struct Breach;
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_atmosphere(
+    mut atmosphere: ResMut<Atmosphere>,
+    scheduler: Res<TurnScheduler>,
+    floor: Res<Grid<Floor>>,
+    impassable: Res<Grid<Impassable>>,
+    walls: Query<(), (With<Impassable>, Without<Health>)>,
+    breaches: Query<&GridLocation, With<Breach>>,
+    mut breathers: Query<(Entity, &GridLocation, &mut Health)>,
+    mut floor_changes: EventReader<DirtyGridEvent<Floor>>,
+    mut damage: EventWriter<Damage>,
+) {
+    // A new level brings its own air
+    if atmosphere.size != floor.size() {
+        atmosphere.reset(&floor);
+    }
+    // Newly laid floor gets filled, floor that's torn up is left to vent
+    for event in floor_changes.read() {
+        let location = &event.0;
+        if let Some(air) = atmosphere.get_mut(location) {
+            if floor.occupied(location) && air.total() <= f32::EPSILON {
+                *air = GasMix::station_air();
+            }
+        }
+    }
+    if atmosphere.next_update > scheduler.clock {
+        return;
+    }
+
+    let points = floor.all_points();
+    // People don't stop gas, walls and machines do
+    let sealed = points
+        .iter()
+        .map(|location| {
+            impassable
+                .get(location)
+                .iter()
+                .any(|entity| walls.contains(*entity))
+        })
+        .collect::<Vec<_>>();
+    let mut space = points
+        .iter()
+        .map(|location| !floor.occupied(location))
+        .collect::<Vec<_>>();
+    for location in &breaches {
+        if let Some(index) = atmosphere.index(location) {
+            space[index] = true;
+        }
+    }
+
+    while atmosphere.next_update <= scheduler.clock {
+        atmosphere.next_update += MOVE_COST;
+        atmosphere.step(&sealed, &space);
+
+        for (entity, location, mut health) in &mut breathers {
+            if health.vitality == Vitality::Dead {
+                continue;
+            }
+            let Some(air) = atmosphere.get_mut(location) else {
+                continue;
+            };
+            let breath = air.oxygen.min(BREATH);
+            air.oxygen -= breath;
+            air.carbon_dioxide += breath;
+
+            let mut hurt = |kind, amount| {
+                damage.send(Damage {
+                    target: entity,
+                    kind,
+                    amount,
+                })
+            };
+            if air.partial_pressure(air.oxygen) < SAFE_OXYGEN_PRESSURE {
+                hurt(DamageKind::Oxygen, SUFFOCATION_DAMAGE);
+            } else if health.oxygen > 0 {
+                // Good air slowly undoes suffocation
+                health.oxygen -= OXYGEN_RECOVERY.min(health.oxygen);
+            }
+            if air.partial_pressure(air.plasma) > SAFE_PLASMA_PRESSURE {
+                hurt(DamageKind::Toxin, TOXIN_DAMAGE);
+            }
+            // Vacuum is cold but there is nothing in it to take the heat away
+            if air.total() > f32::EPSILON && !SAFE_TEMPERATURE.contains(&air.temperature) {
+                hurt(DamageKind::Burn, TEMPERATURE_DAMAGE);
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Runs the game without a window for a number of turns and prints where it ended up.
+//! The scripted scenarios live in `tests/`.
+//! `cargo run --bin headless -- [seed] [turns]`
+
+use logic_ss_13::{atmos::Atmosphere, headless::HeadlessGame};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let seed = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(0);
+    let turns = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(50);
+
+    let mut game = HeadlessGame::new(seed);
+    game.wait_turns(turns);
+
+    let location = game.player_location();
+    let air = game.world().resource::<Atmosphere>().get(&location);
+    println!("Seed {} finished at time {}", seed, game.clock());
+    println!("Air at the player is {:.1} kPa", air.pressure());
+    println!(
+        "Player at {:?} holding {:?}",
+        game.player_location(),
+        game.held_names()
+    );
+    for entry in game.log() {
+        println!("{}", entry);
+    }
+}
@@ -0,0 +1,214 @@
+use bevy::{ecs::system::Command, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access::{carried_access, has_access, IdCard},
+    fov::Opaque,
+    graphics::{GameSprite, Impassable},
+    grid::{Grid, GridLocation, LockToGrid},
+    hands::Hands,
+    health::Health,
+    inventory::Inventory,
+    items::ItemTags,
+    log::AddToLog,
+    player::Player,
+    turn::{TurnScheduler, MOVE_COST},
+};
+
+const CLOSED_GLYPH: char = 'D';
+const OPEN_GLYPH: char = '\'';
+// Turns a door stays open before it swings shut again, once nothing is standing in it
+const DOOR_OPEN_TURNS: u64 = 5;
+const AIRLOCK_OPEN_TURNS: u64 = 3;
+
+/// Blocks movement, sight and air while closed. Bumping into it opens it unless it's
+/// bolted or needs access the opener doesn't have
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+    /// Access level needed to open it, anyone can open it when empty
+    #[serde(default)]
+    pub access: Option<String>,
+    pub closes_at: u64,
+}
+
+impl Door {
+    pub fn with_access(access: &str) -> Self {
+        Self {
+            access: Some(access.to_string()),
+            ..default()
+        }
+    }
+
+    /// Whether someone carrying `access` could walk up and open it
+    pub fn openable(&self, access: &[String]) -> bool {
+        !self.locked
+            && self
+                .access
+                .as_ref()
+                .is_none_or(|needed| has_access(access, needed))
+    }
+}
+
+/// One of the two doors either side of an airlock, it won't open while the other one is open
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Airlock {
+    pub partner: Entity,
+}
+
+pub fn spawn_door(
+    commands: &mut Commands,
+    location: GridLocation,
+    name: &str,
+    door: Door,
+) -> Entity {
+    commands
+        .spawn((
+            location,
+            LockToGrid,
+            Name::new(name.to_string()),
+            door,
+            ItemTags(vec!["door".to_string()]),
+            Impassable,
+            Opaque,
+            GameSprite::Text(CLOSED_GLYPH),
+            SpatialBundle::default(),
+        ))
+        .id()
+}
+
+/// Two doors that take turns opening
+pub fn spawn_airlock(
+    commands: &mut Commands,
+    first: GridLocation,
+    second: GridLocation,
+) -> [Entity; 2] {
+    let first = spawn_door(commands, first, "Airlock", Door::default());
+    let second = spawn_door(commands, second, "Airlock", Door::default());
+    commands.entity(first).insert(Airlock { partner: second });
+    commands.entity(second).insert(Airlock { partner: first });
+    [first, second]
+}
+
+/// Closed doors that npcs carrying `access` will open on their way somewhere
+pub fn npc_passable(
+    grid: &Grid<Impassable>,
+    doors: &Query<&Door>,
+    location: &GridLocation,
+    access: &[String],
+) -> Option<Entity> {
+    let occupants = grid.get(location);
+    match occupants {
+        [door] => doors
+            .get(*door)
+            .is_ok_and(|door| !door.open && door.openable(access))
+            .then_some(*door),
+        _ => None,
+    }
+}
+
+pub struct OpenDoor {
+    pub door: Entity,
+    pub opener: Entity,
+}
+
+impl Command for OpenDoor {
+    fn apply(self, world: &mut World) {
+        let Some(door) = world.get::<Door>(self.door).cloned() else {
+            return;
+        };
+        if door.open {
+            return;
+        }
+        let partner_open = world
+            .get::<Airlock>(self.door)
+            .and_then(|airlock| world.get::<Door>(airlock.partner))
+            .is_some_and(|partner| partner.open);
+        let refusal = if door.locked {
+            Some("The door is bolted shut")
+        } else if !door.openable(&carried_access(
+            world.get::<Hands>(self.opener),
+            world.get::<Inventory>(self.opener),
+            |held| world.get::<IdCard>(held),
+        )) {
+            Some("Access denied")
+        } else if partner_open {
+            Some("The other airlock door is still open")
+        } else {
+            None
+        };
+        if let Some(refusal) = refusal {
+            if world.get::<Player>(self.opener).is_some() {
+                AddToLog(refusal.to_string(), None).apply(world);
+            }
+            return;
+        }
+
+        let turns = if world.get::<Airlock>(self.door).is_some() {
+            AIRLOCK_OPEN_TURNS
+        } else {
+            DOOR_OPEN_TURNS
+        };
+        let clock = world.resource::<TurnScheduler>().clock;
+        let mut entity = world.entity_mut(self.door);
+        entity
+            .insert((
+                Door {
+                    open: true,
+                    closes_at: clock + turns * MOVE_COST,
+                    ..door
+                },
+                GameSprite::Text(OPEN_GLYPH),
+            ))
+            .remove::<(Impassable, Opaque)>();
+    }
+}
+
+pub struct CloseDoor(pub Entity);
+
+impl Command for CloseDoor {
+    fn apply(self, world: &mut World) {
+        let Some(mut door) = world.get_mut::<Door>(self.0) else {
+            return;
+        };
+        door.open = false;
+        world
+            .entity_mut(self.0)
+            .insert((Impassable, Opaque, GameSprite::Text(CLOSED_GLYPH)));
+    }
+}
+
+/// Bolts or unbolts a door, bolted doors don't open for anyone
+pub struct ToggleBolts(pub Entity);
+
+impl Command for ToggleBolts {
+    fn apply(self, world: &mut World) {
+        let Some(mut door) = world.get_mut::<Door>(self.0) else {
+            return;
+        };
+        door.locked = !door.locked;
+        let message = if door.locked {
+            "The door bolts drop"
+        } else {
+            "The door bolts rise"
+        };
+        AddToLog(message.to_string(), None).apply(world);
+    }
+}
+
+pub fn close_doors(
+    mut commands: Commands,
+    scheduler: Res<TurnScheduler>,
+    doors: Query<(Entity, &Door, &GridLocation)>,
+    actors: Query<&GridLocation, With<Health>>,
+) {
+    for (entity, door, location) in &doors {
+        // Never shut on someone standing in the doorway, going by where actors are now since the
+        // grid only catches up with this frame's moves later on
+        let standing = actors.iter().any(|actor| actor == location);
+        if door.open && door.closes_at <= scheduler.clock && !standing {
+            commands.add(CloseDoor(entity));
+        }
+    }
+}
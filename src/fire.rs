@@ -0,0 +1,217 @@
+use bevy::{ecs::system::Command, hierarchy::despawn_with_children_recursive, prelude::*};
+use bevy_turborand::{DelegatedRng, GlobalRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    atmos::Atmosphere,
+    graphics::{GameSprite, Impassable, TintOverride},
+    grid::{Grid, GridLocation, LockToGrid},
+    hands::Hands,
+    health::{Damage, DamageKind},
+    inventory::{spill_contents, Container, Inventory},
+    items::ReplaceItem,
+    log::AddToLog,
+    map::MapTile,
+    turn::{Energy, TurnScheduler, MOVE_COST, NPC_ACTION_COST},
+    Item,
+};
+
+pub const FIRE_TINT: Color = Color::ORANGE;
+// Percent chance each turn that something burning puts out smoke
+const SMOKE_CHANCE: u32 = 30;
+const SMOKE_TURNS: u64 = 3;
+// Burn damage is a fraction of the fire's heat
+const HEAT_PER_DAMAGE: u32 = 5;
+// Fires smother below this much oxygen, in kPa, and turn what they burn into carbon dioxide
+const MIN_OXYGEN_PRESSURE: f32 = 5.0;
+const OXYGEN_BURNED: f32 = 0.5;
+// Kelvin added to the tile each turn per point of heat
+const HEATING: f32 = 0.1;
+
+/// Things that can catch fire. `heat` is the percent chance per turn of spreading to each
+/// flammable neighbor and also sets how badly the fire burns whoever stands in it
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Flammable {
+    pub fuel: u64,
+    pub heat: u32,
+    /// Item definition left behind once the fuel is gone, otherwise it burns away
+    #[serde(default)]
+    pub burns_into: Option<String>,
+}
+
+/// Burns for `fuel` more turns, taking its turns through the scheduler like any other actor
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Burning {
+    pub fuel: u64,
+    pub heat: u32,
+}
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Smoke {
+    pub clears_at: u64,
+}
+
+/// Sets something flammable alight, does nothing if it can't burn or already is
+pub struct Ignite(pub Entity);
+
+impl Command for Ignite {
+    fn apply(self, world: &mut World) {
+        let Some(flammable) = world.get::<Flammable>(self.0).cloned() else {
+            return;
+        };
+        if world.get::<Burning>(self.0).is_some() {
+            return;
+        }
+        let clock = world.resource::<TurnScheduler>().clock;
+        let mut energy = Energy::default();
+        energy.ready_at = clock + energy.cost(NPC_ACTION_COST);
+        world.entity_mut(self.0).insert((
+            Burning {
+                fuel: flammable.fuel,
+                heat: flammable.heat,
+            },
+            energy,
+            TintOverride(FIRE_TINT),
+        ));
+    }
+}
+
+pub struct BurnOut(pub Entity);
+
+impl Command for BurnOut {
+    fn apply(self, world: &mut World) {
+        let Some(mut entity) = world.get_entity_mut(self.0) else {
+            return;
+        };
+        entity.remove::<(Burning, Energy)>();
+        let flammable = entity.get::<Flammable>().cloned();
+        let name = entity.get::<Name>().map(|name| name.to_lowercase());
+        let is_item = entity.contains::<Item>();
+        if let Some(name) = name {
+            AddToLog(format!("The {} burns out", name), None).apply(world);
+        }
+
+        match flammable.and_then(|flammable| flammable.burns_into) {
+            Some(id) => ReplaceItem { entity: self.0, id }.apply(world),
+            None if is_item => {
+                // Contents fall out where it burned before it goes
+                let stored = world
+                    .entity_mut(self.0)
+                    .take::<Container>()
+                    .map(|container| container.contents)
+                    .unwrap_or_default();
+                spill_contents(world, self.0, stored);
+                for mut hands in world.query::<&mut Hands>().iter_mut(world) {
+                    hands.release(self.0);
+                }
+                for mut inventory in world.query::<&mut Inventory>().iter_mut(world) {
+                    inventory.release(self.0);
+                }
+                for mut container in world.query::<&mut Container>().iter_mut(world) {
+                    container.contents.retain(|stored| *stored != self.0);
+                }
+                despawn_with_children_recursive(world, self.0);
+            }
+            // Scorched tiles stay but won't burn again
+            None => {
+                world
+                    .entity_mut(self.0)
+                    .remove::<(Flammable, TintOverride)>();
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn burn(
+    mut commands: Commands,
+    scheduler: Res<TurnScheduler>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut burning: Query<(Entity, &mut Burning, Option<&GridLocation>)>,
+    holders: Query<(&Hands, &GridLocation)>,
+    flammable_grid: Res<Grid<Flammable>>,
+    unlit: Query<(), (With<Flammable>, Without<Burning>)>,
+    actor_grid: Res<Grid<Impassable>>,
+    mut atmosphere: ResMut<Atmosphere>,
+    mut damage: EventWriter<Damage>,
+) {
+    for (entity, mut burning, location) in &mut burning {
+        if !scheduler.is_acting(entity) {
+            continue;
+        }
+        burning.fuel = burning.fuel.saturating_sub(1);
+
+        // Carried fires still smoke but only ones on the ground spread or hurt anyone
+        let held_at = holders
+            .iter()
+            .find(|(hands, _)| hands.hands.iter().any(|hand| hand.holding == Some(entity)))
+            .map(|(_, location)| location);
+        if let Some(air) = location.or(held_at).and_then(|at| atmosphere.get_mut(at)) {
+            if air.partial_pressure(air.oxygen) < MIN_OXYGEN_PRESSURE {
+                commands.add(BurnOut(entity));
+                continue;
+            }
+            let burned = air.oxygen.min(OXYGEN_BURNED);
+            air.oxygen -= burned;
+            air.carbon_dioxide += burned;
+            air.temperature += burning.heat as f32 * HEATING;
+        }
+        if let Some(smoke_at) = location.or(held_at) {
+            if global_rng.u32(0..100) < SMOKE_CHANCE {
+                commands.spawn((
+                    Smoke {
+                        clears_at: scheduler.clock + SMOKE_TURNS * MOVE_COST,
+                    },
+                    smoke_at.clone(),
+                    LockToGrid,
+                    MapTile,
+                    GameSprite::Text('~'),
+                    TintOverride(Color::GRAY),
+                    SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, 700.0)),
+                ));
+            }
+        }
+
+        if let Some(location) = location {
+            let center = location.get_location();
+            for offset in [IVec2::ZERO, IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let cell = GridLocation::from(center + offset);
+                if !flammable_grid.valid_index(&cell) {
+                    continue;
+                }
+                for other in flammable_grid[&cell].iter().flatten() {
+                    if unlit.contains(*other) && global_rng.u32(0..100) < burning.heat {
+                        commands.add(Ignite(*other));
+                    }
+                }
+            }
+
+            let amount = burning.heat / HEAT_PER_DAMAGE;
+            if amount > 0 && actor_grid.valid_index(location) {
+                for target in actor_grid[location].iter().flatten() {
+                    damage.send(Damage {
+                        target: *target,
+                        kind: DamageKind::Burn,
+                        amount,
+                    });
+                }
+            }
+        }
+
+        if burning.fuel == 0 {
+            commands.add(BurnOut(entity));
+        }
+    }
+}
+
+pub fn clear_smoke(
+    mut commands: Commands,
+    scheduler: Res<TurnScheduler>,
+    smoke: Query<(Entity, &Smoke)>,
+) {
+    for (entity, smoke) in &smoke {
+        if smoke.clears_at <= scheduler.clock {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+
+use crate::{
+    graphics::{Dimmed, GameSprite},
+    grid::{DirtyGridEvent, Grid, GridLocation},
+    player::Player,
+    Npc,
+};
+
+pub const FOV_RADIUS: i32 = 12;
+
+/// Blocks sight, kept separate from `Impassable` so glass can stop movement but not vision
+#[derive(Component, Default, Clone, Copy)]
+pub struct Opaque;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TileVisibility {
+    #[default]
+    Unseen,
+    Remembered,
+    Visible,
+}
+
+/// What the player can see right now and what they remember seeing
+#[derive(Resource, Default)]
+pub struct FieldOfView {
+    tiles: Vec<TileVisibility>,
+    size: UVec2,
+}
+
+impl FieldOfView {
+    fn index(&self, location: &GridLocation) -> Option<usize> {
+        (location.x >= 0
+            && location.y >= 0
+            && location.x < self.size.x as i32
+            && location.y < self.size.y as i32)
+            .then(|| location.x as usize * self.size.y as usize + location.y as usize)
+    }
+
+    pub fn get(&self, location: &GridLocation) -> TileVisibility {
+        self.index(location)
+            .map(|index| self.tiles[index])
+            .unwrap_or_default()
+    }
+
+    pub fn is_visible(&self, location: &GridLocation) -> bool {
+        self.get(location) == TileVisibility::Visible
+    }
+
+    fn reset(&mut self, size: UVec2) {
+        self.size = size;
+        self.tiles = vec![TileVisibility::Unseen; (size.x * size.y) as usize];
+    }
+
+    fn mark_visible(&mut self, location: &GridLocation) {
+        if let Some(index) = self.index(location) {
+            self.tiles[index] = TileVisibility::Visible;
+        }
+    }
+}
+
+// Octant transforms for recursive shadowcasting
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, -1],
+    [0, 1, -1, 0],
+    [0, -1, -1, 0],
+    [-1, 0, 0, -1],
+    [-1, 0, 0, 1],
+    [0, -1, 1, 0],
+    [0, 1, 1, 0],
+    [1, 0, 0, 1],
+];
+
+/// Calls `visible` for every tile in sight of the origin, tiles outside the grid block sight
+pub fn shadowcast<T>(
+    grid: &Grid<T>,
+    origin: &GridLocation,
+    radius: i32,
+    mut visible: impl FnMut(GridLocation),
+) {
+    visible(origin.clone());
+    for [xx, xy, yx, yy] in OCTANTS {
+        cast_light(
+            grid,
+            origin.get_location(),
+            1,
+            1.0,
+            0.0,
+            radius,
+            (xx, xy, yx, yy),
+            &mut visible,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light<T>(
+    grid: &Grid<T>,
+    origin: IVec2,
+    row: i32,
+    mut start: f32,
+    end: f32,
+    radius: i32,
+    transform: (i32, i32, i32, i32),
+    visible: &mut impl FnMut(GridLocation),
+) {
+    if start < end {
+        return;
+    }
+    let (xx, xy, yx, yy) = transform;
+    let blocks = |location: &GridLocation| !grid.valid_index(location) || grid.occupied(location);
+
+    let mut new_start = 0.0;
+    for distance in row..=radius {
+        let dy = -distance;
+        let mut blocked = false;
+        for dx in -distance..=0 {
+            let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if start < right_slope {
+                continue;
+            } else if end > left_slope {
+                break;
+            }
+
+            let location =
+                GridLocation::from(origin + IVec2::new(dx * xx + dy * xy, dx * yx + dy * yy));
+            if dx * dx + dy * dy <= radius * radius && grid.valid_index(&location) {
+                visible(location.clone());
+            }
+
+            if blocked {
+                if blocks(&location) {
+                    new_start = right_slope;
+                } else {
+                    blocked = false;
+                    start = new_start;
+                }
+            } else if blocks(&location) && distance < radius {
+                blocked = true;
+                cast_light(
+                    grid,
+                    origin,
+                    distance + 1,
+                    start,
+                    left_slope,
+                    radius,
+                    transform,
+                    visible,
+                );
+                new_start = right_slope;
+            }
+        }
+        if blocked {
+            break;
+        }
+    }
+}
+
+pub fn update_fov(
+    mut fov: ResMut<FieldOfView>,
+    grid: Res<Grid<Opaque>>,
+    player: Query<Ref<GridLocation>, With<Player>>,
+    mut dirty: EventReader<DirtyGridEvent<Opaque>>,
+) {
+    let Ok(location) = player.get_single() else {
+        return;
+    };
+    let walls_changed = dirty.read().count() > 0;
+    // A new level means forgetting the old one
+    let resized = fov.size != grid.size();
+    if !resized && !walls_changed && !location.is_changed() {
+        return;
+    }
+    if resized {
+        fov.reset(grid.size());
+    }
+
+    for tile in fov.tiles.iter_mut() {
+        if *tile == TileVisibility::Visible {
+            *tile = TileVisibility::Remembered;
+        }
+    }
+    shadowcast(&grid, &location, FOV_RADIUS, |seen| fov.mark_visible(&seen));
+}
+
+pub fn apply_fov(
+    mut commands: Commands,
+    fov: Res<FieldOfView>,
+    mut sprites: Query<
+        (
+            Entity,
+            Ref<GridLocation>,
+            &mut Visibility,
+            Option<&mut Dimmed>,
+            Has<Npc>,
+        ),
+        (With<GameSprite>, Without<Player>),
+    >,
+) {
+    for (entity, location, mut visibility, dimmed, is_npc) in &mut sprites {
+        if !fov.is_changed() && !location.is_changed() {
+            continue;
+        }
+        // Things that move around are only shown while in sight
+        let (shown, dim) = match fov.get(&location) {
+            TileVisibility::Visible => (true, false),
+            TileVisibility::Remembered => (!is_npc, true),
+            TileVisibility::Unseen => (false, false),
+        };
+        let new_visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != new_visibility {
+            *visibility = new_visibility;
+        }
+        match dimmed {
+            Some(mut dimmed) => {
+                if dimmed.0 != dim {
+                    dimmed.0 = dim;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(Dimmed(dim));
+            }
+        }
+    }
+}
@@ -11,16 +11,18 @@ use bevy::render::render_resource::{
     Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
 };
 use bevy_inspector_egui::bevy_egui::EguiUserTextures;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    grid::{GRID_SIZE_X, GRID_SIZE_Y},
-    SCREEN_SIZE_X, SCREEN_SIZE_Y, SCREEN_TILE_SIZE_X, SCREEN_TILE_SIZE_Y, TILE_SIZE,
+    atmos::{Atmosphere, ONE_ATMOSPHERE},
+    grid::{Grid, GridLocation, ResizeGrid},
+    Floor, TILE_SIZE,
 };
 
 #[derive(Component, Default, Clone, Copy)]
 pub struct Impassable;
 
-#[derive(Component, Default, Hash, PartialEq, Eq, Debug)]
+#[derive(Component, Default, Hash, PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum GameSprite {
     #[default]
     Player,
@@ -33,7 +35,7 @@ pub enum GameSprite {
     Border(BorderDirection),
 }
 
-#[derive(Component, Hash, PartialEq, Eq, Debug)]
+#[derive(Component, Hash, PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
 pub enum BorderDirection {
     Top,
     Bottom,
@@ -45,9 +47,15 @@ pub enum BorderDirection {
     BottomRight,
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct TintOverride(pub Color);
 
+/// Darkens the sprite, used for tiles the player remembers but can't currently see
+#[derive(Component, Clone, Copy, Default, PartialEq)]
+pub struct Dimmed(pub bool);
+
+const DIM_FACTOR: f32 = 0.35;
+
 #[derive(Resource, Default)]
 pub struct SpriteMap {
     map: HashMap<GameSprite, (Handle<TextureAtlas>, usize, Color)>,
@@ -61,18 +69,27 @@ pub fn update_sprites(
             &GameSprite,
             Option<&mut TextureAtlasSprite>,
             Option<&TintOverride>,
+            Option<&Dimmed>,
         ),
-        Or<(Changed<GameSprite>, Changed<TintOverride>)>,
+        Or<(Changed<GameSprite>, Changed<TintOverride>, Changed<Dimmed>)>,
     >,
     map: Res<SpriteMap>,
 ) {
     let first_pass_layer = RenderLayers::layer(1);
-    for (entity, sprite, texture_atlas, tint) in &mut sprites {
-        let color = if let Some(tint) = tint {
+    for (entity, sprite, texture_atlas, tint, dimmed) in &mut sprites {
+        let mut color = if let Some(tint) = tint {
             tint.0
         } else {
             map.map[sprite].2
         };
+        if dimmed.is_some_and(|dimmed| dimmed.0) {
+            color = Color::rgba(
+                color.r() * DIM_FACTOR,
+                color.g() * DIM_FACTOR,
+                color.b() * DIM_FACTOR,
+                color.a(),
+            );
+        }
 
         match texture_atlas {
             Some(mut atlas) => {
@@ -86,7 +103,7 @@ pub fn update_sprites(
                         color,
                         ..default()
                     },
-                    first_pass_layer.clone(),
+                    first_pass_layer,
                     map.map[sprite].0.clone(),
                 ));
             }
@@ -216,9 +233,8 @@ pub fn setup(
         ),
     );
 
-    let alphabet = ('a'..='z').chain('A'..='Z').chain(" ><_-=+:;\"!".chars());
-
-    for c in alphabet {
+    // Every printable character, the atlas follows ascii order
+    for c in ' '..='~' {
         map.map.insert(
             GameSprite::Text(c),
             (
@@ -235,18 +251,28 @@ pub fn setup(
 #[derive(Resource)]
 pub struct GameRender(pub Handle<Image>);
 
+/// The camera drawing the map into the `GameRender` texture
+#[derive(Component)]
+pub struct GameCamera;
+
+fn game_render_size(map_size: UVec2) -> (u32, u32, Extent3d) {
+    let width = map_size.x * TILE_SIZE as u32;
+    let height = map_size.y * TILE_SIZE as u32;
+    let size = Extent3d {
+        width: width * 2,
+        height: height * 2,
+        ..default()
+    };
+    (width, height, size)
+}
+
 pub fn camera_setup(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     mut egui_user_textures: ResMut<EguiUserTextures>,
+    grid: Res<Grid<Floor>>,
 ) {
-    let width = GRID_SIZE_X as u32 * TILE_SIZE as u32;
-    let height = GRID_SIZE_Y as u32 * TILE_SIZE as u32;
-    let size = Extent3d {
-        width: width* 2,
-        height: height *2 ,
-        ..default()
-    };
+    let (width, height, size) = game_render_size(grid.size());
 
     // This is the texture that will be rendered to.
     let mut image = Image {
@@ -276,7 +302,7 @@ pub fn camera_setup(
     let first_pass_layer = RenderLayers::layer(1);
 
     let center_x = width as f32 / 2.0 - TILE_SIZE / 2.0;
-    let center_y = height as f32 / 2.0 - TILE_SIZE / 2.0 ;
+    let center_y = height as f32 / 2.0 - TILE_SIZE / 2.0;
 
     commands.spawn((
         Camera2dBundle {
@@ -300,6 +326,7 @@ pub fn camera_setup(
             ..default()
         },
         first_pass_layer,
+        GameCamera,
     ));
 
     commands.insert_resource(GameRender(image_handle));
@@ -313,3 +340,109 @@ pub fn camera_setup(
     camera.transform = Transform::from_xyz(center_x, center_y, 0.0);
     commands.spawn(camera);
 }
+
+pub fn resize_game_render(
+    mut events: EventReader<ResizeGrid>,
+    game_render: Res<GameRender>,
+    mut images: ResMut<Assets<Image>>,
+    mut camera: Query<(&mut OrthographicProjection, &mut Transform), With<GameCamera>>,
+) {
+    let Some(ResizeGrid(map_size)) = events.read().last() else {
+        return;
+    };
+    let (width, height, size) = game_render_size(*map_size);
+
+    if let Some(image) = images.get_mut(&game_render.0) {
+        image.resize(size);
+    }
+    for (mut projection, mut transform) in &mut camera {
+        projection.scaling_mode = ScalingMode::Fixed {
+            width: width as f32,
+            height: height as f32,
+        };
+        transform.translation.x = width as f32 / 2.0 - TILE_SIZE / 2.0;
+        transform.translation.y = height as f32 / 2.0 - TILE_SIZE / 2.0;
+    }
+}
+
+// Drawn over the map but under the central menu
+const OVERLAY_DEPTH: f32 = 800.0;
+const OVERLAY_ALPHA: f32 = 0.4;
+
+/// Tints every tile by its air, blue where it's breathable fading to red in vacuum,
+/// with plasma showing up purple
+#[derive(Resource, Default)]
+pub struct AtmosOverlay {
+    pub enabled: bool,
+}
+
+#[derive(Component)]
+pub struct AtmosOverlayTile;
+
+pub fn toggle_atmos_overlay(mut overlay: ResMut<AtmosOverlay>, keyboard: Res<Input<KeyCode>>) {
+    if keyboard.just_pressed(KeyCode::O) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+pub fn draw_atmos_overlay(
+    mut commands: Commands,
+    overlay: Res<AtmosOverlay>,
+    atmosphere: Res<Atmosphere>,
+    mut tiles: Query<(Entity, &GridLocation, &mut Sprite, &mut Visibility), With<AtmosOverlayTile>>,
+) {
+    if !overlay.is_changed() && !atmosphere.is_changed() {
+        return;
+    }
+    let size = atmosphere.size();
+    // One sprite per tile, rebuilt whenever the map changes size
+    if tiles.iter().count() != (size.x * size.y) as usize {
+        for (entity, ..) in &tiles {
+            commands.entity(entity).despawn_recursive();
+        }
+        for x in 0..size.x {
+            for y in 0..size.y {
+                commands.spawn((
+                    AtmosOverlayTile,
+                    GridLocation::new(x, y),
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::NONE,
+                            custom_size: Some(Vec2::splat(TILE_SIZE)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(
+                            x as f32 * TILE_SIZE,
+                            y as f32 * TILE_SIZE,
+                            OVERLAY_DEPTH,
+                        ),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    RenderLayers::layer(1),
+                ));
+            }
+        }
+        return;
+    }
+
+    for (_, location, mut sprite, mut visibility) in &mut tiles {
+        *visibility = if overlay.enabled {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        if !overlay.enabled {
+            continue;
+        }
+        let air = atmosphere.get(location);
+        let pressure = (air.pressure() / ONE_ATMOSPHERE).clamp(0.0, 1.0);
+        let plasma = (air.plasma / air.total().max(f32::EPSILON)).clamp(0.0, 1.0);
+        sprite.color = Color::rgba(
+            (1.0 - pressure + plasma).min(1.0),
+            0.1,
+            pressure,
+            OVERLAY_ALPHA,
+        );
+    }
+}
@@ -1,37 +1,38 @@
-use std::{
-    collections::HashSet,
-    marker::PhantomData,
-    ops::{Index, IndexMut},
-};
-
-use bevy::{
-    prelude::*,
-    tasks::{AsyncComputeTaskPool, Task},
-    transform::TransformSystem,
-};
-use futures_lite::future;
-use pathfinding::undirected::connected_components;
+use std::{collections::HashMap, marker::PhantomData, ops::Index};
+
+use bevy::{prelude::*, transform::TransformSystem, utils::HashSet};
+use pathfinding::{directed::astar::astar, undirected::connected_components};
 use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::TILE_SIZE;
 
-// TODO Make this a generic on the plugin or otherwise configurable
-pub const GRID_SIZE_X: usize = 60;
-pub const GRID_SIZE_Y: usize = 36;
+pub const DEFAULT_GRID_SIZE: UVec2 = UVec2::new(60, 36);
 
 #[derive(Resource)]
 pub struct Grid<T> {
-    entities: [[Option<Vec<Entity>>; GRID_SIZE_Y]; GRID_SIZE_X],
+    // Column major, indexed by x * height + y
+    entities: Vec<Option<Vec<Entity>>>,
+    // Reverse lookup so finding an entity does not scan every cell
+    locations: HashMap<Entity, GridLocation>,
+    size: UVec2,
     _marker: PhantomData<T>,
 }
 
+/// Open regions of the grid, kept up to date incrementally from `DirtyGridEvent`s.
+/// Layers like cables join up their occupied cells instead, see `GridPlugin::connect_occupied`
 #[derive(Resource)]
 pub struct ConnectedComponents<T> {
-    pub components: Vec<HashSet<GridLocation>>,
+    pub components: HashMap<usize, HashSet<GridLocation>>,
+    // Component id of every cell, laid out like `Grid`, None for cells outside any region
+    labels: Vec<Option<usize>>,
+    size: UVec2,
+    next_id: usize,
+    occupied: bool,
     _marker: PhantomData<T>,
 }
 
-#[derive(Component, Eq, PartialEq, Hash, Clone, Debug, Deref, DerefMut)]
+#[derive(Component, Eq, PartialEq, Hash, Clone, Debug, Deref, DerefMut, Serialize, Deserialize)]
 pub struct GridLocation(IVec2);
 
 #[derive(Debug)]
@@ -43,8 +44,12 @@ impl GridLocation {
     pub fn get_location(&self) -> IVec2 {
         self.0
     }
-    pub fn try_set_location(&mut self, new_location: IVec2) -> Result<(), GridLocationError> {
-        if Grid::<()>::valid_index(&GridLocation(new_location)) {
+    pub fn try_set_location<T>(
+        &mut self,
+        new_location: IVec2,
+        grid: &Grid<T>,
+    ) -> Result<(), GridLocationError> {
+        if grid.valid_index(&GridLocation(new_location)) {
             self.0 = new_location;
             Ok(())
         } else {
@@ -60,30 +65,73 @@ pub struct LockToGrid;
 #[derive(Event)]
 pub struct DirtyGridEvent<T>(pub GridLocation, PhantomData<T>);
 
-#[derive(Default)]
+impl<T> DirtyGridEvent<T> {
+    pub fn new(location: GridLocation) -> Self {
+        Self(location, PhantomData)
+    }
+}
+
+/// Systems keeping every grid layer in sync with the world
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GridSystems;
+
+/// Resizes every grid layer, entities that fall outside the new bounds are dropped from the grids
+#[derive(Event, Clone, Copy)]
+pub struct ResizeGrid(pub UVec2);
+
 pub struct GridPlugin<T> {
+    size: UVec2,
+    connect_occupied: bool,
     _marker: PhantomData<T>,
 }
 
+impl<T> GridPlugin<T> {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            connect_occupied: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Connected components are built from occupied cells rather than open ones
+    pub fn connect_occupied(mut self) -> Self {
+        self.connect_occupied = true;
+        self
+    }
+}
+
+impl<T> Default for GridPlugin<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRID_SIZE)
+    }
+}
+
 impl<T: Component> Plugin for GridPlugin<T> {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Grid<T>>()
-            .init_resource::<ConnectedComponents<T>>()
-            .add_systems(PostUpdate, update_connected_components::<T>)
+        app.insert_resource(Grid::<T>::new(self.size))
+            .add_event::<ResizeGrid>()
+            .insert_resource(ConnectedComponents::<T> {
+                occupied: self.connect_occupied,
+                ..default()
+            })
             .add_event::<DirtyGridEvent<T>>()
             // TODO move_on_grid / GridLocation change detection
-            .add_systems(Startup, first_dirty_event::<T>)
             .add_systems(
                 PostUpdate,
                 (
+                    resize_grid::<T>.before(add_to_grid::<T>),
                     add_to_grid::<T>,
                     lock_to_grid::<T>
                         .after(update_in_grid::<T>)
                         .before(TransformSystem::TransformPropagate),
                     update_in_grid::<T>.after(add_to_grid::<T>),
                     remove_from_grid::<T>,
-                    resolve_connected_components::<T>,
-                ),
+                    update_connected_components::<T>
+                        .after(update_in_grid::<T>)
+                        .after(remove_from_grid::<T>),
+                )
+                    .in_set(GridSystems),
             );
     }
 }
@@ -103,62 +151,32 @@ fn lock_to_grid<T: Component>(
     }
 }
 
-// Forces some sane initializations of connected components
-fn first_dirty_event<T: Component>(mut dirty: EventWriter<DirtyGridEvent<T>>) {
-    dirty.send(DirtyGridEvent::<T>(GridLocation::new(0, 0), PhantomData));
-}
-
-#[derive(Component)]
-struct ConnectedTask<T> {
-    task: Task<ConnectedComponents<T>>,
-}
-
-fn resolve_connected_components<T: Component>(
-    mut commands: Commands,
-    mut connected: ResMut<ConnectedComponents<T>>,
-    // Should maybe be a resource?
-    mut tasks: Query<(Entity, &mut ConnectedTask<T>)>,
+fn resize_grid<T: Component>(
+    mut grid: ResMut<Grid<T>>,
+    mut events: EventReader<ResizeGrid>,
+    mut dirty: EventWriter<DirtyGridEvent<T>>,
 ) {
-    for (task_entity, mut task) in &mut tasks {
-        if let Some(result) = future::block_on(future::poll_once(&mut task.task)) {
-            //TODO is there a way to make bevy auto remove these or not panic or something
-            commands.entity(task_entity).despawn_recursive();
-            *connected = result;
+    if let Some(ResizeGrid(size)) = events.read().last() {
+        if *size != grid.size() {
+            grid.resize(*size);
+            dirty.send(DirtyGridEvent::<T>(GridLocation::new(0, 0), PhantomData));
         }
     }
 }
 
 fn update_connected_components<T: Component>(
-    mut commands: Commands,
     grid: Res<Grid<T>>,
+    mut connected: ResMut<ConnectedComponents<T>>,
     mut events: EventReader<DirtyGridEvent<T>>,
-    // Should maybe be a resource?
-    current_tasks: Query<Entity, With<ConnectedTask<T>>>,
 ) {
-    if !events.is_empty() {
+    // A new or resized grid has nothing to build on
+    if connected.size != grid.size() {
         events.clear();
-        for task in &current_tasks {
-            commands.entity(task).despawn_recursive();
-        }
-
-        let thread_pool = AsyncComputeTaskPool::get();
-        let grid = Box::new(grid.clone());
-
-        let task = thread_pool.spawn(async move {
-            let starts = all_points()
-                .into_iter()
-                .filter(|point| !grid.occupied(point))
-                .collect::<Vec<_>>();
-
-            ConnectedComponents::<T> {
-                components: connected_components::connected_components(&starts, |p| {
-                    neumann_neighbors(&grid, p)
-                }),
-                ..default()
-            }
-        });
-
-        commands.spawn(ConnectedTask { task });
+        connected.rebuild(&grid);
+        return;
+    }
+    for DirtyGridEvent(location, _) in events.read() {
+        connected.update_cell(&grid, location);
     }
 }
 
@@ -168,42 +186,47 @@ fn remove_from_grid<T: Component>(
     mut query2: RemovedComponents<GridLocation>,
     mut dirty: EventWriter<DirtyGridEvent<T>>,
 ) {
-    for removed_entity in query.read() {
-        // Search for entity
-        let removed = grid.iter().find(|(entity, _)| *entity == removed_entity);
-        if let Some((_, location)) = removed {
-            dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-            if let Some(entities) = grid[&location].as_mut() {
-                entities.retain(|grid_ent| removed_entity != *grid_ent);
-            }
-        }
-    }
-    for removed_entity in query2.read() {
-        // Search for entity
-        let removed = grid.iter().find(|(entity, _)| *entity == removed_entity);
-        if let Some((_, location)) = removed {
-            dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-            if let Some(entities) = grid[&location].as_mut() {
-                entities.retain(|grid_ent| removed_entity != *grid_ent);
-            }
+    for removed_entity in query.read().chain(query2.read()) {
+        if let Some(location) = grid.remove(removed_entity) {
+            dirty.send(DirtyGridEvent::<T>(location, PhantomData));
         }
     }
 }
 
 impl<T> Grid<T> {
     pub fn force_update(&mut self, entity: Entity, new_location: &GridLocation) {
-        if let Some(previous) = self.find_in_grid(entity) {
-            self[&previous] = None;
+        self.remove(entity);
+        self.insert(entity, new_location);
+    }
+
+    /// Removes only this entity from its cell, leaving anything sharing the tile in place
+    pub fn remove(&mut self, entity: Entity) -> Option<GridLocation> {
+        let location = self.locations.remove(&entity)?;
+        let cell = self.cell_mut(&location);
+        if let Some(entities) = cell.as_mut() {
+            entities.retain(|grid_ent| entity != *grid_ent);
+            if entities.is_empty() {
+                *cell = None;
+            }
         }
-        if Grid::<()>::valid_index(new_location) {
-            if let Some(ref mut existing) = &mut self[new_location] {
-                if !existing.contains(&entity) {
-                    existing.push(entity);
-                }
-            } else {
-                self[new_location] = Some(vec![entity]);
+        Some(location)
+    }
+
+    /// Adds the entity to the cell, returning false if it was already there or the location is invalid
+    fn insert(&mut self, entity: Entity, location: &GridLocation) -> bool {
+        if !self.valid_index(location) {
+            return false;
+        }
+        if let Some(ref mut existing) = self.cell_mut(location) {
+            if existing.contains(&entity) {
+                return false;
             }
+            existing.push(entity);
+        } else {
+            *self.cell_mut(location) = Some(vec![entity]);
         }
+        self.locations.insert(entity, location.clone());
+        true
     }
 }
 
@@ -213,13 +236,12 @@ fn update_in_grid<T: Component>(
     mut dirty: EventWriter<DirtyGridEvent<T>>,
 ) {
     for (entity, location) in &query {
-        if Grid::<()>::valid_index(location)
-            && grid[location]
-                .as_ref()
-                .map(|vec| !vec.contains(&entity))
-                .unwrap_or(true)
-        {
+        let previous = grid.find_in_grid(entity);
+        if grid.valid_index(location) && previous.as_ref() != Some(location) {
             grid.force_update(entity, location);
+            if let Some(previous) = previous {
+                dirty.send(DirtyGridEvent::<T>(previous, PhantomData));
+            }
             dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
         }
     }
@@ -231,44 +253,21 @@ fn add_to_grid<T: Component>(
     query2: Query<(Entity, &GridLocation), (With<T>, Added<GridLocation>)>,
     mut dirty: EventWriter<DirtyGridEvent<T>>,
 ) {
-    for (entity, location) in &query {
-        if Grid::<()>::valid_index(location) {
-            if let Some(ref mut existing) = &mut grid[location] {
-                if !existing.contains(&entity) {
-                    dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-                    existing.push(entity);
-                }
-            } else {
-                dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-                grid[location] = Some(vec![entity]);
-            }
-        }
-    }
-    for (entity, location) in &query2 {
-        if Grid::<()>::valid_index(location) {
-            if let Some(ref mut existing) = &mut grid[location] {
-                if !existing.contains(&entity) {
-                    dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-                    existing.push(entity);
-                }
-            } else {
-                dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
-                grid[location] = Some(vec![entity]);
-            }
+    for (entity, location) in query.iter().chain(query2.iter()) {
+        if grid.insert(entity, location) {
+            dirty.send(DirtyGridEvent::<T>(location.clone(), PhantomData));
         }
     }
 }
 
-fn all_points() -> Vec<GridLocation> {
-    (0..GRID_SIZE_X)
-        .flat_map(|x| (0..GRID_SIZE_Y).map(move |y| GridLocation::new(x as u32, y as u32)))
-        .collect()
-}
-
 impl<T> Default for ConnectedComponents<T> {
     fn default() -> Self {
         Self {
             components: Default::default(),
+            labels: Default::default(),
+            size: UVec2::ZERO,
+            next_id: 0,
+            occupied: false,
             _marker: Default::default(),
         }
     }
@@ -278,21 +277,16 @@ impl<T> Clone for Grid<T> {
     fn clone(&self) -> Self {
         Self {
             entities: self.entities.clone(),
+            locations: self.locations.clone(),
+            size: self.size,
             _marker: self._marker,
         }
     }
 }
 
-// https://github.com/rust-lang/rust/issues/44796#issuecomment-967747810
-const INIT: Option<Vec<Entity>> = None;
-const INIT_INNER: [Option<Vec<Entity>>; GRID_SIZE_Y] = [INIT; GRID_SIZE_Y];
-
 impl<T> Default for Grid<T> {
     fn default() -> Self {
-        Self {
-            entities: [INIT_INNER; GRID_SIZE_X],
-            _marker: Default::default(),
-        }
+        Self::new(DEFAULT_GRID_SIZE)
     }
 }
 
@@ -321,42 +315,86 @@ impl From<IVec2> for GridLocation {
 }
 
 impl<T> Grid<T> {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            entities: vec![None; (size.x * size.y) as usize],
+            locations: HashMap::new(),
+            size,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Clears the grid to the new size and re-adds every entity that still fits
+    pub fn resize(&mut self, size: UVec2) {
+        let old = std::mem::replace(self, Grid::new(size));
+        for (entity, location) in old.iter() {
+            self.force_update(entity, &location);
+        }
+    }
+
+    pub fn all_points(&self) -> Vec<GridLocation> {
+        let height = self.size.y;
+        (0..self.size.x)
+            .flat_map(|x| (0..height).map(move |y| GridLocation::new(x, y)))
+            .collect()
+    }
+
     pub fn occupied(&self, location: &GridLocation) -> bool {
-        Grid::<T>::valid_index(location) && self[location].is_some()
+        self.valid_index(location) && self[location].is_some()
     }
 
-    pub fn valid_index(location: &GridLocation) -> bool {
+    pub fn valid_index(&self, location: &GridLocation) -> bool {
         location.x >= 0
             && location.y >= 0
-            && location.x < GRID_SIZE_X as i32
-            && location.y < GRID_SIZE_Y as i32
+            && location.x < self.size.x as i32
+            && location.y < self.size.y as i32
     }
 
-    pub fn find_in_grid(&self, to_find: Entity) -> Option<GridLocation> {
-        for (entity, location) in self.iter() {
-            if entity == to_find {
-                return Some(location);
-            }
+    /// Entities at the location, empty when there are none or it's off the grid
+    pub fn get(&self, location: &GridLocation) -> &[Entity] {
+        if !self.valid_index(location) {
+            return &[];
         }
-        None
+        self[location].as_deref().unwrap_or(&[])
+    }
+
+    pub fn find_in_grid(&self, to_find: Entity) -> Option<GridLocation> {
+        self.locations.get(&to_find).cloned()
+    }
+
+    /// Flat index of the cell, a location off the grid would otherwise land in the next column
+    fn flat_index(&self, location: &GridLocation) -> usize {
+        assert!(
+            self.valid_index(location),
+            "{:?} is outside the {} grid",
+            location.get_location(),
+            self.size
+        );
+        location.x as usize * self.size.y as usize + location.y as usize
+    }
+
+    fn cell_mut(&mut self, location: &GridLocation) -> &mut Option<Vec<Entity>> {
+        let index = self.flat_index(location);
+        &mut self.entities[index]
     }
 }
 
 impl<T> Grid<T> {
     pub fn iter(&self) -> impl Iterator<Item = (Entity, GridLocation)> + '_ {
+        let height = self.size.y;
         self.entities
             .iter()
-            .flatten()
             .enumerate()
-            .filter_map(|(i, cell)| {
+            .filter_map(move |(i, cell)| {
                 cell.as_ref().map(|entities| {
                     entities.iter().map(move |&entity| {
                         (
                             entity,
-                            GridLocation::new(
-                                i as u32 / GRID_SIZE_Y as u32,
-                                i as u32 % GRID_SIZE_Y as u32,
-                            ),
+                            GridLocation::new(i as u32 / height, i as u32 % height),
                         )
                     })
                 })
@@ -369,26 +407,162 @@ impl<T> Index<&GridLocation> for Grid<T> {
     type Output = Option<Vec<Entity>>;
 
     fn index(&self, index: &GridLocation) -> &Self::Output {
-        &self.entities[index.x as usize][index.y as usize]
+        &self.entities[self.flat_index(index)]
     }
 }
 
-impl<T> IndexMut<&GridLocation> for Grid<T> {
-    fn index_mut(&mut self, index: &GridLocation) -> &mut Self::Output {
-        &mut self.entities[index.x as usize][index.y as usize]
+impl<T> ConnectedComponents<T> {
+    fn cell_index(&self, location: &GridLocation) -> Option<usize> {
+        (location.x >= 0
+            && location.y >= 0
+            && location.x < self.size.x as i32
+            && location.y < self.size.y as i32)
+            .then(|| location.x as usize * self.size.y as usize + location.y as usize)
+    }
+
+    pub fn component_id(&self, location: &GridLocation) -> Option<usize> {
+        self.cell_index(location)
+            .and_then(|index| self.labels[index])
     }
-}
 
-impl<T> ConnectedComponents<T> {
     #[allow(unused)]
     pub fn point_to_component(&self, start: &GridLocation) -> Option<&HashSet<GridLocation>> {
-        self.components
-            .iter()
-            .find(|component| component.contains(start))
+        self.component_id(start)
+            .and_then(|id| self.components.get(&id))
     }
 
     pub fn is_in_same_component(&self, start: &GridLocation, end: &GridLocation) -> bool {
-        self.point_to_component(start) == self.point_to_component(end)
+        self.component_id(start) == self.component_id(end)
+    }
+
+    fn in_region(&self, grid: &Grid<T>, location: &GridLocation) -> bool {
+        grid.occupied(location) == self.occupied
+    }
+
+    fn neighbors(&self, grid: &Grid<T>, location: &GridLocation) -> Vec<GridLocation> {
+        neumann_neighbors_where(grid, location, self.occupied)
+    }
+
+    fn rebuild(&mut self, grid: &Grid<T>) {
+        self.size = grid.size();
+        self.labels = vec![None; (self.size.x * self.size.y) as usize];
+        self.components.clear();
+
+        let starts = grid
+            .all_points()
+            .into_iter()
+            .filter(|point| self.in_region(grid, point))
+            .collect::<Vec<_>>();
+        for component in
+            connected_components::connected_components(&starts, |p| self.neighbors(grid, p))
+        {
+            self.insert_component(component);
+        }
+    }
+
+    fn insert_component(&mut self, component: impl IntoIterator<Item = GridLocation>) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let points = component.into_iter().collect::<HashSet<_>>();
+        for location in &points {
+            if let Some(index) = self.cell_index(location) {
+                self.labels[index] = Some(id);
+            }
+        }
+        self.components.insert(id, points);
+        id
+    }
+
+    /// Brings a single cell in line with the grid, only touching the components around it
+    fn update_cell(&mut self, grid: &Grid<T>, location: &GridLocation) {
+        let Some(index) = self.cell_index(location) else {
+            return;
+        };
+        match (self.in_region(grid, location), self.labels[index]) {
+            (true, None) => self.open_cell(grid, location, index),
+            (false, Some(id)) => self.close_cell(grid, location, index, id),
+            _ => {}
+        }
+    }
+
+    // Joins every neighboring component into the largest one, relabeling the smaller ones
+    fn open_cell(&mut self, grid: &Grid<T>, location: &GridLocation, index: usize) {
+        let mut neighbors = self
+            .neighbors(grid, location)
+            .iter()
+            .filter_map(|neighbor| self.component_id(neighbor))
+            .collect::<Vec<_>>();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+
+        let Some(&largest) = neighbors
+            .iter()
+            .max_by_key(|id| self.components.get(id).map_or(0, HashSet::len))
+        else {
+            self.insert_component([location.clone()]);
+            return;
+        };
+
+        for id in neighbors.into_iter().filter(|id| *id != largest) {
+            let Some(merged) = self.components.remove(&id) else {
+                continue;
+            };
+            for point in &merged {
+                if let Some(point_index) = self.cell_index(point) {
+                    self.labels[point_index] = Some(largest);
+                }
+            }
+            if let Some(component) = self.components.get_mut(&largest) {
+                component.extend(merged);
+            }
+        }
+        self.labels[index] = Some(largest);
+        if let Some(component) = self.components.get_mut(&largest) {
+            component.insert(location.clone());
+        }
+    }
+
+    // Closing a cell can split its component, refill only that component to find the pieces
+    fn close_cell(&mut self, grid: &Grid<T>, location: &GridLocation, index: usize, id: usize) {
+        self.labels[index] = None;
+        let Some(mut remaining) = self.components.remove(&id) else {
+            return;
+        };
+        remaining.remove(location);
+        // Other cells closed this frame are still in here until their own events come round,
+        // the fill can't pass through them so they're dropped now and their events find nothing
+        let closed = remaining
+            .iter()
+            .filter(|point| !self.in_region(grid, point))
+            .cloned()
+            .collect::<Vec<_>>();
+        for point in &closed {
+            remaining.remove(point);
+            if let Some(point_index) = self.cell_index(point) {
+                self.labels[point_index] = None;
+            }
+        }
+
+        let starts = self
+            .neighbors(grid, location)
+            .into_iter()
+            .filter(|neighbor| remaining.contains(neighbor))
+            .collect::<Vec<_>>();
+        if closed.is_empty() && starts.len() <= 1 {
+            self.components.insert(id, remaining);
+            return;
+        }
+
+        // Filling from every point leaves nothing with the old id behind
+        let starts = remaining.iter().cloned().collect::<Vec<_>>();
+        for piece in connected_components::connected_components(&starts, |p| {
+            self.neighbors(grid, p)
+                .into_iter()
+                .filter(|neighbor| remaining.contains(neighbor))
+                .collect::<Vec<_>>()
+        }) {
+            self.insert_component(piece);
+        }
     }
 
     pub fn get_random_point_in_same_component<R>(
@@ -399,40 +573,431 @@ impl<T> ConnectedComponents<T> {
     where
         R: Rng + ?Sized,
     {
-        self.point_to_component(start)
-            .and_then(|component| component.iter().choose(rng).cloned())
+        let id = self.component_id(start)?;
+        let height = self.size.y as usize;
+        // Walks the labels rather than the hash set, whose order changes between runs, so a
+        // seeded rng always picks the same point
+        self.labels
+            .iter()
+            .enumerate()
+            .filter(|(_, label)| **label == Some(id))
+            .map(|(index, _)| GridLocation::new((index / height) as u32, (index % height) as u32))
+            .choose(rng)
     }
 }
 
 pub fn neumann_neighbors<T>(grid: &Grid<T>, location: &GridLocation) -> Vec<GridLocation> {
+    neumann_neighbors_where(grid, location, false)
+}
+
+/// Four way neighbors that are occupied, or free when `occupied` is false
+pub fn neumann_neighbors_where<T>(
+    grid: &Grid<T>,
+    location: &GridLocation,
+    occupied: bool,
+) -> Vec<GridLocation> {
     let (x, y) = (location.x as u32, location.y as u32);
 
     let mut successors = Vec::new();
     if let Some(left) = x.checked_sub(1) {
         let location = GridLocation::new(left, y);
-        if !grid.occupied(&location) {
+        if grid.occupied(&location) == occupied {
             successors.push(location);
         }
     }
     if let Some(down) = y.checked_sub(1) {
         let location = GridLocation::new(x, down);
-        if !grid.occupied(&location) {
+        if grid.occupied(&location) == occupied {
             successors.push(location);
         }
     }
-    if x + 1 < GRID_SIZE_X as u32 {
+    if x + 1 < grid.size.x {
         let right = x + 1;
         let location = GridLocation::new(right, y);
-        if !grid.occupied(&location) {
+        if grid.occupied(&location) == occupied {
             successors.push(location);
         }
     }
-    if y + 1 < GRID_SIZE_Y as u32 {
+    if y + 1 < grid.size.y {
         let up = y + 1;
         let location = GridLocation::new(x, up);
-        if !grid.occupied(&location) {
+        if grid.occupied(&location) == occupied {
             successors.push(location);
         }
     }
     successors
 }
+
+impl<T> Grid<T> {
+    /// Shortest four way path from start to goal, excluding start, with every step costing 1
+    pub fn path(&self, start: &GridLocation, goal: &GridLocation) -> Option<Vec<GridLocation>> {
+        self.path_with_costs(start, goal, false, |_| 1)
+            .map(|(path, _)| path)
+    }
+
+    /// A* from start to goal, excluding start, along with the total cost.
+    /// The goal may be occupied so things can path up to what they want to interact with.
+    /// Costs are per tile entered and should be at least 1 to keep the heuristic admissible.
+    pub fn path_with_costs<F>(
+        &self,
+        start: &GridLocation,
+        goal: &GridLocation,
+        diagonal: bool,
+        cost: F,
+    ) -> Option<(Vec<GridLocation>, u32)>
+    where
+        F: Fn(&GridLocation) -> u32,
+    {
+        self.path_through(start, goal, diagonal, |_| false, cost)
+    }
+
+    /// Like `path_with_costs` but occupied tiles where `passable` holds can be walked through,
+    /// for things like doors that open on the way
+    pub fn path_through<P, F>(
+        &self,
+        start: &GridLocation,
+        goal: &GridLocation,
+        diagonal: bool,
+        passable: P,
+        cost: F,
+    ) -> Option<(Vec<GridLocation>, u32)>
+    where
+        P: Fn(&GridLocation) -> bool,
+        F: Fn(&GridLocation) -> u32,
+    {
+        let (mut path, total) = astar(
+            start,
+            |point| {
+                self.path_neighbors(point, goal, diagonal, &passable)
+                    .into_iter()
+                    .map(|next| {
+                        let step = cost(&next);
+                        (next, step)
+                    })
+                    .collect::<Vec<_>>()
+            },
+            |point| {
+                let distance = (point.get_location() - goal.get_location()).abs();
+                if diagonal {
+                    distance.max_element() as u32
+                } else {
+                    (distance.x + distance.y) as u32
+                }
+            },
+            |point| point == goal,
+        )?;
+        path.remove(0);
+        Some((path, total))
+    }
+
+    fn path_neighbors(
+        &self,
+        location: &GridLocation,
+        goal: &GridLocation,
+        diagonal: bool,
+        passable: &impl Fn(&GridLocation) -> bool,
+    ) -> Vec<GridLocation> {
+        let open = |point: &GridLocation| {
+            self.valid_index(point) && (!self.occupied(point) || point == goal || passable(point))
+        };
+        let mut neighbors = Vec::new();
+        for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let point = GridLocation(location.get_location() + offset);
+            if open(&point) {
+                neighbors.push(point);
+            }
+        }
+        if diagonal {
+            for offset in [
+                IVec2::ONE,
+                IVec2::NEG_ONE,
+                IVec2::new(1, -1),
+                IVec2::new(-1, 1),
+            ] {
+                let point = GridLocation(location.get_location() + offset);
+                // No squeezing between two blocked corners
+                let side_x = GridLocation(location.get_location() + IVec2::new(offset.x, 0));
+                let side_y = GridLocation(location.get_location() + IVec2::new(0, offset.y));
+                if open(&point) && open(&side_x) && open(&side_y) {
+                    neighbors.push(point);
+                }
+            }
+        }
+        neighbors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hands::{handle_give_item, GiveItem, Hands};
+
+    fn grid_with(size: UVec2, placed: &[(u32, (u32, u32))]) -> Grid<()> {
+        let mut grid = Grid::new(size);
+        for (index, (x, y)) in placed {
+            grid.insert(Entity::from_raw(*index), &GridLocation::new(*x, *y));
+        }
+        grid
+    }
+
+    /// The reverse index has to agree with the cells about where everything is
+    fn assert_in_sync(grid: &Grid<()>) {
+        let mut from_cells = grid.iter().collect::<Vec<_>>();
+        let mut from_index = grid
+            .locations
+            .iter()
+            .map(|(entity, location)| (*entity, location.clone()))
+            .collect::<Vec<_>>();
+        from_cells.sort_by_key(|(entity, _)| *entity);
+        from_index.sort_by_key(|(entity, _)| *entity);
+        assert_eq!(from_cells, from_index);
+    }
+
+    #[test]
+    fn insert_is_indexed() {
+        let grid = grid_with(UVec2::new(4, 4), &[(0, (1, 2)), (1, (1, 2)), (2, (3, 0))]);
+        assert_eq!(
+            grid.find_in_grid(Entity::from_raw(1)),
+            Some(GridLocation::new(1, 2))
+        );
+        assert_eq!(grid.get(&GridLocation::new(1, 2)).len(), 2);
+        assert_in_sync(&grid);
+    }
+
+    #[test]
+    fn move_updates_index() {
+        let mut grid = grid_with(UVec2::new(4, 4), &[(0, (1, 2)), (1, (1, 2))]);
+        grid.force_update(Entity::from_raw(0), &GridLocation::new(2, 2));
+        assert_eq!(
+            grid.find_in_grid(Entity::from_raw(0)),
+            Some(GridLocation::new(2, 2))
+        );
+        assert_eq!(grid.get(&GridLocation::new(1, 2)), &[Entity::from_raw(1)]);
+        assert_in_sync(&grid);
+    }
+
+    #[test]
+    fn remove_updates_index() {
+        let mut grid = grid_with(UVec2::new(4, 4), &[(0, (1, 2)), (1, (3, 3))]);
+        assert_eq!(
+            grid.remove(Entity::from_raw(0)),
+            Some(GridLocation::new(1, 2))
+        );
+        assert_eq!(grid.remove(Entity::from_raw(0)), None);
+        assert!(!grid.occupied(&GridLocation::new(1, 2)));
+        assert_eq!(grid.find_in_grid(Entity::from_raw(0)), None);
+        assert_in_sync(&grid);
+    }
+
+    #[test]
+    fn resize_drops_what_no_longer_fits() {
+        let mut grid = grid_with(UVec2::new(4, 4), &[(0, (1, 2)), (1, (3, 3))]);
+        grid.resize(UVec2::new(3, 5));
+        assert_eq!(
+            grid.find_in_grid(Entity::from_raw(0)),
+            Some(GridLocation::new(1, 2))
+        );
+        assert_eq!(grid.find_in_grid(Entity::from_raw(1)), None);
+        assert_in_sync(&grid);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_outside_grid_panics() {
+        let grid = grid_with(UVec2::new(4, 4), &[]);
+        let _ = &grid[&GridLocation::new(0, 4)];
+    }
+
+    #[test]
+    fn move_touches_only_its_two_cells() {
+        let size = UVec2::new(1000, 1000);
+        let mut grid = grid_with(size, &[(0, (0, 0)), (1, (size.x - 1, size.y - 1))]);
+        let before = grid.entities.clone();
+        grid.force_update(Entity::from_raw(0), &GridLocation::new(1, 0));
+        let changed = before
+            .iter()
+            .zip(&grid.entities)
+            .filter(|(before, after)| before != after)
+            .count();
+        assert_eq!(changed, 2);
+        assert_eq!(grid.locations.len(), 2);
+    }
+
+    #[test]
+    fn lookup_reads_the_index_not_the_cells() {
+        let mut grid = grid_with(UVec2::new(1000, 1000), &[(0, (999, 999))]);
+        // Were lookups scanning the map they would come up empty now
+        grid.entities.iter_mut().for_each(|cell| *cell = None);
+        assert_eq!(
+            grid.find_in_grid(Entity::from_raw(0)),
+            Some(GridLocation::new(999, 999))
+        );
+    }
+
+    fn components_of(grid: &Grid<()>) -> ConnectedComponents<()> {
+        let mut components = ConnectedComponents::default();
+        components.rebuild(grid);
+        components
+    }
+
+    /// Groups of cells sharing a label, ids can differ between builds but the groups can't
+    fn partition(components: &ConnectedComponents<()>) -> Vec<Vec<usize>> {
+        let mut groups = HashMap::<usize, Vec<usize>>::new();
+        for (index, label) in components.labels.iter().enumerate() {
+            if let Some(id) = label {
+                assert!(
+                    components.components.contains_key(id),
+                    "cell {} points at missing component {}",
+                    index,
+                    id
+                );
+                groups.entry(*id).or_default().push(index);
+            }
+        }
+        let mut groups = groups.into_values().collect::<Vec<_>>();
+        groups.sort();
+        groups
+    }
+
+    /// Flips every listed cell in the grid, then lets the components catch up on all of them
+    fn toggle(grid: &mut Grid<()>, components: &mut ConnectedComponents<()>, cells: &[(u32, u32)]) {
+        for (x, y) in cells {
+            let location = GridLocation::new(*x, *y);
+            let wall = Entity::from_raw(x * 1000 + y);
+            if grid.remove(wall).is_none() {
+                grid.insert(wall, &location);
+            }
+        }
+        for (x, y) in cells {
+            components.update_cell(grid, &GridLocation::new(*x, *y));
+        }
+        assert_eq!(partition(components), partition(&components_of(grid)));
+    }
+
+    #[test]
+    fn closing_a_cell_splits_its_component() {
+        let mut grid = grid_with(UVec2::new(5, 5), &[]);
+        let mut components = components_of(&grid);
+        for y in 0..5 {
+            toggle(&mut grid, &mut components, &[(2, y)]);
+        }
+        assert_eq!(partition(&components).len(), 2);
+        toggle(&mut grid, &mut components, &[(2, 3)]);
+        assert_eq!(partition(&components).len(), 1);
+    }
+
+    #[test]
+    fn cells_closed_together_leave_no_stale_labels() {
+        // A corridor cut in two places at once, the far end is only reached through the other cut
+        let mut grid = grid_with(UVec2::new(5, 1), &[]);
+        let mut components = components_of(&grid);
+        toggle(&mut grid, &mut components, &[(1, 0), (3, 0)]);
+        assert_eq!(partition(&components).len(), 3);
+        toggle(&mut grid, &mut components, &[(1, 0), (3, 0)]);
+        assert_eq!(partition(&components).len(), 1);
+    }
+
+    #[test]
+    fn incremental_matches_rebuild() {
+        let size = UVec2::new(8, 6);
+        let mut grid = grid_with(size, &[]);
+        let mut components = components_of(&grid);
+        // Fixed pseudo random batches of opens and closes
+        let mut state = 7u32;
+        for batch in 0..200 {
+            let cells = (0..batch % 4 + 1)
+                .map(|_| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    let cell = (state >> 8) % (size.x * size.y);
+                    (cell / size.y, cell % size.y)
+                })
+                .collect::<Vec<_>>();
+            let mut unique = cells.clone();
+            unique.sort();
+            unique.dedup();
+            toggle(&mut grid, &mut components, &unique);
+        }
+    }
+
+    #[derive(Component)]
+    struct Marker;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(GridPlugin::<Marker>::new(UVec2::new(8, 8)))
+            .add_event::<GiveItem>()
+            .add_systems(Update, handle_give_item);
+        app
+    }
+
+    fn spawn_at(app: &mut App, x: u32, y: u32) -> Entity {
+        app.world
+            .spawn((Marker, GridLocation::new(x, y), Name::new("Thing")))
+            .id()
+    }
+
+    fn cell(app: &App, x: u32, y: u32) -> Vec<Entity> {
+        app.world
+            .resource::<Grid<Marker>>()
+            .get(&GridLocation::new(x, y))
+            .to_vec()
+    }
+
+    #[test]
+    fn moving_one_of_two_leaves_the_other() {
+        let mut app = app();
+        let mover = spawn_at(&mut app, 1, 1);
+        let stayer = spawn_at(&mut app, 1, 1);
+        app.update();
+        *app.world.get_mut::<GridLocation>(mover).unwrap() = GridLocation::new(2, 1);
+        app.update();
+        assert_eq!(cell(&app, 1, 1), vec![stayer]);
+        assert_eq!(cell(&app, 2, 1), vec![mover]);
+    }
+
+    #[test]
+    fn stacked_items_share_a_cell() {
+        let mut app = app();
+        let stack = (0..3).map(|_| spawn_at(&mut app, 4, 2)).collect::<Vec<_>>();
+        app.update();
+        assert_eq!(cell(&app, 4, 2), stack);
+        app.world.despawn(stack[1]);
+        app.update();
+        assert_eq!(cell(&app, 4, 2), vec![stack[0], stack[2]]);
+    }
+
+    #[test]
+    fn despawn_clears_cell_and_index() {
+        let mut app = app();
+        let entity = spawn_at(&mut app, 3, 3);
+        app.update();
+        app.world.despawn(entity);
+        app.update();
+        assert!(cell(&app, 3, 3).is_empty());
+        let grid = app.world.resource::<Grid<Marker>>();
+        assert!(!grid.locations.contains_key(&entity));
+    }
+
+    #[test]
+    fn picking_up_leaves_the_grid() {
+        let mut app = app();
+        let item = spawn_at(&mut app, 5, 5);
+        let holder = app.world.spawn(Hands::human_hands()).id();
+        app.update();
+        app.world.send_event(GiveItem {
+            receiver: Some(holder),
+            item,
+        });
+        app.update();
+        assert!(app.world.get::<GridLocation>(item).is_none());
+        assert!(cell(&app, 5, 5).is_empty());
+        assert_eq!(
+            app.world.resource::<Grid<Marker>>().find_in_grid(item),
+            None
+        );
+        assert_eq!(
+            app.world.get::<Hands>(holder).unwrap().get_active_held(),
+            Some(item)
+        );
+    }
+}
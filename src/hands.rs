@@ -1,8 +1,9 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{grid::GridLocation, player::Player};
 
-#[derive(Component, Debug, Default, Clone)]
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Hands {
     pub hands: Vec<Hand>,
     pub active: Option<usize>,
@@ -23,9 +24,12 @@ pub fn handle_give_item(
     items: Query<&Name>,
 ) {
     for ev in events.read() {
-        if let Ok(mut receiver) = hands.get_mut(ev.receiver.unwrap_or(player.single())) {
+        let Some(receiver) = ev.receiver.or_else(|| player.get_single().ok()) else {
+            continue;
+        };
+        if let Ok(mut receiver) = hands.get_mut(receiver) {
             if !receiver.can_pickup() {
-                return;
+                continue;
             }
             if items.contains(ev.item) {
                 commands
@@ -57,7 +61,16 @@ impl Hands {
         }
     }
 
-    fn pickup(&mut self, entity: Entity) -> bool {
+    /// Lets go of the entity in whichever hand holds it
+    pub fn release(&mut self, entity: Entity) {
+        for hand in self.hands.iter_mut() {
+            if hand.holding == Some(entity) {
+                hand.holding = None;
+            }
+        }
+    }
+
+    pub fn pickup(&mut self, entity: Entity) -> bool {
         self.active
             .and_then(|idx| self.hands.get_mut(idx))
             .filter(|hand| hand.holding.is_none())
@@ -68,6 +81,11 @@ impl Hands {
             .unwrap_or(false)
     }
 
+    /// Everything held in any hand
+    pub fn held(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.hands.iter().filter_map(|hand| hand.holding)
+    }
+
     pub fn can_pickup(&self) -> bool {
         self.active
             .and_then(|idx| self.hands.get(idx))
@@ -83,7 +101,7 @@ impl Hands {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Hand {
     // TODO must be named
     pub holding: Option<Entity>,
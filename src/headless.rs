@@ -0,0 +1,265 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::Command,
+    input::{keyboard::KeyboardInput, ButtonState, InputPlugin},
+    prelude::*,
+};
+use bevy_turborand::prelude::RngPlugin;
+
+use crate::{
+    grid::{Grid, GridLocation},
+    hands::Hands,
+    inventory::Inventory,
+    items::SpawnItem,
+    log::Log,
+    menu::CentralMenu,
+    player::Player,
+    recipes::{RecipeBook, RecipeLibrary},
+    replay::{PlayerAction, Replay, ReplayMode, ReplayPlugin},
+    turn::{Energy, TurnScheduler, MOVE_COST},
+    wfc::WfcState,
+    GamePlugin,
+};
+
+// Frames an action may take to resolve before giving up, a turn only takes a few hundred ticks
+const MAX_SETTLE_FRAMES: usize = 1000;
+const LOAD_POLL: Duration = Duration::from_millis(5);
+
+/// Runs the game without a window so it can be driven from tests and balancing scripts.
+/// Input goes through the same keyboard events a window would send.
+pub struct HeadlessGame {
+    pub app: App,
+}
+
+impl HeadlessGame {
+    pub fn new(seed: u64) -> Self {
+        Self::build(seed, None)
+    }
+
+    /// Plays the replay back through the keyboard once `run_replay` is called
+    pub fn playback(replay: Replay) -> Self {
+        let seed = replay.seed;
+        Self::build(seed, Some(ReplayPlugin::playback(replay)))
+    }
+
+    fn build(seed: u64, replay: Option<ReplayPlugin>) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            RngPlugin::default().with_rng_seed(seed),
+            GamePlugin,
+        ));
+        if let Some(replay) = replay {
+            app.add_plugins(replay);
+        }
+        app.finish();
+        app.cleanup();
+        let mut game = Self { app };
+        // The player is spawned once item definitions finish loading off thread, recipes load alongside
+        for _ in 0..MAX_SETTLE_FRAMES {
+            game.app.update();
+            if game.try_player().is_some() && game.recipes_loaded() {
+                return game;
+            }
+            std::thread::sleep(LOAD_POLL);
+        }
+        panic!("headless game never spawned a player");
+    }
+
+    pub fn world(&self) -> &World {
+        &self.app.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    /// Presses and releases a key, one frame each
+    pub fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+        self.app.update();
+        self.send_key(key, ButtonState::Released);
+        self.app.update();
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    /// Performs an action and runs the world until the player can act again
+    pub fn act(&mut self, action: PlayerAction) {
+        self.press(action.key());
+        self.settle();
+    }
+
+    pub fn act_all(&mut self, actions: impl IntoIterator<Item = PlayerAction>) {
+        for action in actions {
+            self.act(action);
+        }
+    }
+
+    /// Updates until the player is waiting on input, either for their turn or in a menu
+    pub fn settle(&mut self) {
+        for _ in 0..MAX_SETTLE_FRAMES {
+            if self.menu_open() || self.player_ready() {
+                return;
+            }
+            self.app.update();
+            if self.generating() {
+                std::thread::sleep(LOAD_POLL);
+            }
+        }
+        warn!("Player was not ready after {} frames", MAX_SETTLE_FRAMES);
+    }
+
+    /// Updates until every action of the replay has been played and the world has settled
+    pub fn run_replay(&mut self) {
+        for _ in 0..MAX_SETTLE_FRAMES {
+            if self.app.world.resource::<ReplayMode>().finished() {
+                break;
+            }
+            self.app.update();
+            // Generation finishes off thread
+            if self.generating() {
+                std::thread::sleep(LOAD_POLL);
+            }
+        }
+        // Let the last action take effect before checking on it
+        self.app.update();
+        self.settle();
+    }
+
+    /// Runs whole turns without the player doing anything
+    pub fn wait_turns(&mut self, turns: u64) {
+        let clock = self.clock();
+        let player = self.player();
+        let mut energy = self.app.world.get_mut::<Energy>(player).unwrap();
+        energy.ready_at = clock + energy.cost(turns * MOVE_COST);
+        self.settle();
+    }
+
+    fn recipes_loaded(&self) -> bool {
+        let library = self.app.world.resource::<RecipeLibrary>();
+        self.app
+            .world
+            .resource::<Assets<RecipeBook>>()
+            .contains(&library.book)
+    }
+
+    pub fn menu_open(&self) -> bool {
+        self.app.world.resource::<CentralMenu>().open
+    }
+
+    pub fn player_ready(&self) -> bool {
+        !self.generating() && self.player_component::<Energy>().ready_at <= self.clock()
+    }
+
+    pub fn generating(&self) -> bool {
+        self.app.world.resource::<WfcState>().running()
+    }
+
+    pub fn player(&self) -> Entity {
+        self.try_player().expect("headless game has no player")
+    }
+
+    fn try_player(&self) -> Option<Entity> {
+        self.app
+            .world
+            .iter_entities()
+            .find(|entity| entity.contains::<Player>())
+            .map(|entity| entity.id())
+    }
+
+    fn player_component<T: Component>(&self) -> &T {
+        self.app
+            .world
+            .get::<T>(self.player())
+            .expect("player is missing a component")
+    }
+
+    pub fn player_location(&self) -> GridLocation {
+        self.player_component::<GridLocation>().clone()
+    }
+
+    /// Puts the player straight onto the tile so a scenario doesn't depend on the walk there
+    pub fn teleport(&mut self, location: GridLocation) {
+        let player = self.player();
+        *self.app.world.get_mut::<GridLocation>(player).unwrap() = location;
+        self.app.update();
+        self.settle();
+    }
+
+    /// Spawns the item straight into the player's active hand
+    pub fn give(&mut self, id: &str) -> Entity {
+        let item = self.app.world.spawn_empty().id();
+        SpawnItem {
+            id: id.to_string(),
+            entity: Some(item),
+        }
+        .apply(&mut self.app.world);
+        let player = self.player();
+        let mut hands = self.app.world.get_mut::<Hands>(player).unwrap();
+        assert!(hands.pickup(item), "the player's active hand is full");
+        item
+    }
+
+    pub fn find_named(&self, name: &str) -> Option<Entity> {
+        self.app
+            .world
+            .iter_entities()
+            .find(|entity| {
+                entity
+                    .get::<Name>()
+                    .is_some_and(|found| found.as_str() == name)
+            })
+            .map(|entity| entity.id())
+    }
+
+    /// The first entity with the name, panics when there is none
+    pub fn named(&self, name: &str) -> Entity {
+        self.find_named(name)
+            .unwrap_or_else(|| panic!("nothing is called {}", name))
+    }
+
+    pub fn hands(&self) -> &Hands {
+        self.player_component::<Hands>()
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        self.player_component::<Inventory>()
+    }
+
+    pub fn held_names(&self) -> Vec<String> {
+        self.hands()
+            .hands
+            .iter()
+            .filter_map(|hand| hand.holding)
+            .filter_map(|entity| self.app.world.get::<Name>(entity))
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    pub fn grid<T: Component>(&self) -> &Grid<T> {
+        self.app.world.resource::<Grid<T>>()
+    }
+
+    pub fn log(&self) -> &[String] {
+        &self.app.world.resource::<Log>().entries
+    }
+
+    pub fn logged(&self, entry: &str) -> bool {
+        self.log().iter().any(|logged| logged == entry)
+    }
+
+    pub fn clock(&self) -> u64 {
+        self.app.world.resource::<TurnScheduler>().clock
+    }
+}
@@ -0,0 +1,263 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::GameSprite,
+    log::AddToLog,
+    player::{Player, PlayerTookTurn},
+    turn::{TurnScheduler, MOVE_COST},
+};
+
+// Damage at which someone passes out, and how many times that kills them
+const DEATH_MULTIPLIER: u32 = 2;
+const STUN_TURNS: u64 = 3;
+const POISON_TURNS: u64 = 5;
+// Toxin damage each turn while poisoned
+const POISON_DAMAGE: u32 = 1;
+const UNCONSCIOUS_GLYPH: char = '_';
+const CORPSE_GLYPH: char = '%';
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Vitality {
+    #[default]
+    Alive,
+    Unconscious,
+    Dead,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// Can't act until it wears off
+    Stunned,
+    /// Takes toxin damage every turn until it wears off
+    Poisoned,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Stunned => "Stunned",
+            StatusKind::Poisoned => "Poisoned",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub until: u64,
+    next_tick: u64,
+}
+
+/// Damage is tracked per type, passing out once it adds up to `max` and dying at twice that
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub max: u32,
+    pub brute: u32,
+    pub burn: u32,
+    pub toxin: u32,
+    pub oxygen: u32,
+    pub vitality: Vitality,
+    pub effects: Vec<StatusEffect>,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self {
+            max,
+            brute: 0,
+            burn: 0,
+            toxin: 0,
+            oxygen: 0,
+            vitality: Vitality::Alive,
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn total_damage(&self) -> u32 {
+        self.brute + self.burn + self.toxin + self.oxygen
+    }
+
+    pub fn current(&self) -> u32 {
+        self.max.saturating_sub(self.total_damage())
+    }
+
+    pub fn has_effect(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Starts the effect or extends it if it's already running
+    pub fn add_effect(&mut self, kind: StatusKind, until: u64, clock: u64) {
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.until = effect.until.max(until),
+            None => self.effects.push(StatusEffect {
+                kind,
+                until,
+                next_tick: clock + MOVE_COST,
+            }),
+        }
+    }
+
+    /// Awake, alive and not stunned
+    pub fn can_act(&self) -> bool {
+        self.vitality == Vitality::Alive && !self.has_effect(StatusKind::Stunned)
+    }
+
+    fn vitality_for_damage(&self) -> Vitality {
+        let damage = self.total_damage();
+        if self.vitality == Vitality::Dead || damage >= self.max * DEATH_MULTIPLIER {
+            Vitality::Dead
+        } else if damage >= self.max {
+            Vitality::Unconscious
+        } else {
+            Vitality::Alive
+        }
+    }
+}
+
+/// Unconscious, dead or stunned, the movement and action systems skip anything with this
+#[derive(Component, Clone, Copy, Default)]
+pub struct Incapacitated;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DamageKind {
+    Brute,
+    Burn,
+    /// Counts as burn damage and stuns
+    Shock,
+    Oxygen,
+    /// Poisons on top of the damage itself
+    Toxin,
+}
+
+#[derive(Event)]
+pub struct Damage {
+    pub target: Entity,
+    pub kind: DamageKind,
+    pub amount: u32,
+}
+
+pub fn apply_damage(
+    mut commands: Commands,
+    mut events: EventReader<Damage>,
+    mut targets: Query<(&mut Health, Has<Player>)>,
+    scheduler: Res<TurnScheduler>,
+) {
+    let clock = scheduler.clock;
+    for event in events.read() {
+        let Ok((mut health, is_player)) = targets.get_mut(event.target) else {
+            continue;
+        };
+        if health.vitality == Vitality::Dead {
+            continue;
+        }
+        match event.kind {
+            DamageKind::Brute => health.brute += event.amount,
+            DamageKind::Burn => health.burn += event.amount,
+            DamageKind::Shock => {
+                health.burn += event.amount;
+                health.add_effect(StatusKind::Stunned, clock + STUN_TURNS * MOVE_COST, clock);
+            }
+            DamageKind::Oxygen => health.oxygen += event.amount,
+            DamageKind::Toxin => {
+                health.toxin += event.amount;
+                health.add_effect(
+                    StatusKind::Poisoned,
+                    clock + POISON_TURNS * MOVE_COST,
+                    clock,
+                );
+            }
+        }
+        if is_player {
+            let message = match event.kind {
+                DamageKind::Brute => "You are hit",
+                DamageKind::Burn => "You are burned",
+                DamageKind::Shock => "You are shocked",
+                DamageKind::Oxygen => "You gasp for air",
+                DamageKind::Toxin => "You feel sick",
+            };
+            commands.add(AddToLog(
+                format!("{} ({}/{})", message, health.current(), health.max),
+                None,
+            ));
+        }
+    }
+}
+
+/// Ticks and expires status effects, then knocks out, wakes up or kills whoever's damage calls for it
+pub fn update_health(
+    mut commands: Commands,
+    mut bodies: Query<(
+        Entity,
+        &mut Health,
+        &mut GameSprite,
+        Option<&Name>,
+        Has<Player>,
+        Has<Incapacitated>,
+    )>,
+    scheduler: Res<TurnScheduler>,
+) {
+    let clock = scheduler.clock;
+    for (entity, mut health, mut sprite, name, is_player, incapacitated) in &mut bodies {
+        if health.vitality != Vitality::Dead && !health.effects.is_empty() {
+            let mut poison = 0;
+            for effect in health.effects.iter_mut() {
+                while effect.next_tick <= clock.min(effect.until) {
+                    effect.next_tick += MOVE_COST;
+                    if effect.kind == StatusKind::Poisoned {
+                        poison += POISON_DAMAGE;
+                    }
+                }
+            }
+            health.toxin += poison;
+            health.effects.retain(|effect| effect.until > clock);
+        }
+
+        let vitality = health.vitality_for_damage();
+        if vitality != health.vitality {
+            health.vitality = vitality;
+            let name = name.map_or("Someone".to_string(), |name| {
+                format!("The {}", name.to_lowercase())
+            });
+            let message = match (vitality, is_player) {
+                (Vitality::Alive, true) => "You come to".to_string(),
+                (Vitality::Unconscious, true) => "You pass out".to_string(),
+                (Vitality::Dead, true) => "You die".to_string(),
+                (Vitality::Alive, false) => format!("{} comes to", name),
+                (Vitality::Unconscious, false) => format!("{} collapses", name),
+                (Vitality::Dead, false) => format!("{} dies", name),
+            };
+            commands.add(AddToLog(message, None));
+            *sprite = match vitality {
+                Vitality::Alive if is_player => GameSprite::Player,
+                Vitality::Alive => GameSprite::Npc,
+                Vitality::Unconscious => GameSprite::Text(UNCONSCIOUS_GLYPH),
+                Vitality::Dead => GameSprite::Text(CORPSE_GLYPH),
+            };
+        }
+
+        if health.can_act() == incapacitated {
+            if incapacitated {
+                commands.entity(entity).remove::<Incapacitated>();
+            } else {
+                commands.entity(entity).insert(Incapacitated);
+            }
+        }
+    }
+}
+
+/// Time keeps moving while the player is out
+pub fn pass_incapacitated_turn(
+    player: Query<(), (With<Player>, With<Incapacitated>)>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+) {
+    if !player.is_empty() {
+        turn_event.send(PlayerTookTurn(MOVE_COST));
+    }
+}
+
+pub fn player_can_act() -> impl Condition<()> {
+    IntoSystem::into_system(
+        |player: Query<(), (With<Player>, Without<Incapacitated>)>| !player.is_empty(),
+    )
+}
@@ -1,24 +1,32 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    access::{carried_access, has_access, IdCard, RequiresAccess},
     graphics::TintOverride,
     grid::{Grid, GridLocation},
-    hands::GiveItem,
+    hands::{GiveItem, Hands},
+    health::{Damage, DamageKind},
+    inventory::Inventory,
     log::AddToLog,
+    maintenance::{Anchored, MaintenancePanel},
     menu::{CentralMenu, CloseMenu, MenuRedraw, OpenMenu},
     player::{Player, PlayerInteract},
+    power::PowerConsumer,
+    turn::TurnScheduler,
+    wiring::{Wiring, WiringMenu, SHOCK_DAMAGE},
 };
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Interactable {
     #[default]
     VendingMachine,
+    /// Lockers and crates, see `storage::Storage`
+    Storage,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
 pub struct VendingMachine {
-    // Wiring inside if panel is open
-    // every ship has a master wiring diagram somewhere
     pub selection: usize,
     pub options: Vec<Entity>,
 }
@@ -50,7 +58,8 @@ pub fn vending_machine_menu(
     mut commands: Commands,
     menu: Res<CentralMenu>,
     player: Query<&GridLocation, With<Player>>,
-    mut machines: Query<&mut VendingMachine>,
+    mut machines: Query<(&mut VendingMachine, Option<&Wiring>, Option<&PowerConsumer>)>,
+    scheduler: Res<TurnScheduler>,
     input: Res<Input<KeyCode>>,
     mut close_menu: EventWriter<CloseMenu>,
     mut redraw_menu: EventWriter<MenuRedraw>,
@@ -58,13 +67,22 @@ pub fn vending_machine_menu(
     names: Query<&Name>,
 ) {
     // TODO remove unwrap
-    if let Ok(mut machine) = machines.get_mut(menu.owner.unwrap()) {
+    if let Ok((mut machine, wiring, power)) = machines.get_mut(menu.owner.unwrap()) {
         if input.just_pressed(KeyCode::Return) {
             let selection = machine.selection;
             if selection >= machine.options.len() {
                 close_menu.send(CloseMenu);
                 return;
             }
+            let powered = power.is_none_or(|power| power.powered);
+            if !powered || wiring.is_some_and(|wiring| !wiring.can_dispense(scheduler.clock)) {
+                commands.add(AddToLog(
+                    "The vending machine whirs but nothing comes out".to_string(),
+                    None,
+                ));
+                close_menu.send(CloseMenu);
+                return;
+            }
             let player_location = player.single();
             let entity = machine.options.remove(selection);
             let name = names.get(entity).unwrap();
@@ -92,17 +110,74 @@ pub fn vending_machine_menu(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn player_interact(
+    mut commands: Commands,
     mut interact: EventReader<PlayerInteract>,
     grid: Res<Grid<Interactable>>,
+    machines: Query<(
+        Option<&Name>,
+        Option<&MaintenancePanel>,
+        Option<&Anchored>,
+        Option<&Wiring>,
+        Option<&PowerConsumer>,
+        Option<&RequiresAccess>,
+    )>,
+    player: Query<(Entity, Option<&Hands>, Option<&Inventory>), With<Player>>,
+    cards: Query<&IdCard>,
+    scheduler: Res<TurnScheduler>,
     mut open_menu: EventWriter<OpenMenu>,
+    mut damage: EventWriter<Damage>,
 ) {
+    let clock = scheduler.clock;
     for event in interact.read() {
         if let Some(entities) = grid[&event.0].as_ref() {
             entities.iter().for_each(|entity| {
                 // TODO if multiple make player select
                 info!("Player interacted with me");
-                open_menu.send(OpenMenu(*entity));
+                let Ok((name, panel, anchored, wiring, power, required)) = machines.get(*entity)
+                else {
+                    return;
+                };
+                // Wiring can cut a machine off from a network that is live
+                let powered = power.is_none_or(|power| power.powered)
+                    && wiring.is_none_or(|wiring| wiring.powered(clock));
+                let name = name.map_or("machine".to_string(), |name| name.to_lowercase());
+                if panel.is_some_and(|panel| panel.open) {
+                    let menu = commands.spawn(WiringMenu::new(*entity)).id();
+                    open_menu.send(OpenMenu(menu));
+                } else if powered && wiring.is_some_and(|wiring| wiring.electrified(clock)) {
+                    for (target, _, _) in &player {
+                        damage.send(Damage {
+                            target,
+                            kind: DamageKind::Shock,
+                            amount: SHOCK_DAMAGE,
+                        });
+                    }
+                } else if anchored.is_some_and(|anchored| !anchored.0) {
+                    commands.add(AddToLog(
+                        format!("The {} rocks on its loose bolts", name),
+                        None,
+                    ));
+                } else if !powered {
+                    commands.add(AddToLog(format!("The {} is dark", name), None));
+                } else if wiring.is_some_and(|wiring| wiring.locked(clock)) {
+                    commands.add(AddToLog(
+                        format!("The {}'s controls are locked", name),
+                        None,
+                    ));
+                } else if required.is_some_and(|required| {
+                    !player.iter().any(|(_, hands, inventory)| {
+                        has_access(
+                            &carried_access(hands, inventory, |held| cards.get(held).ok()),
+                            &required.0,
+                        )
+                    })
+                }) {
+                    commands.add(AddToLog("Access denied".to_string(), None));
+                } else {
+                    open_menu.send(OpenMenu(*entity));
+                }
             });
         }
     }
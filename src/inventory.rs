@@ -0,0 +1,414 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access::IdCard,
+    graphics::TintOverride,
+    grid::{GridLocation, LockToGrid},
+    hands::Hands,
+    log::AddToLog,
+    menu::{CentralMenu, CloseMenu, MenuRedraw, OpenMenu},
+    player::{Player, PlayerTookTurn},
+    turn::PICKUP_COST,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Head,
+    Suit,
+    Belt,
+    Back,
+    Id,
+    LeftPocket,
+    RightPocket,
+}
+
+impl EquipSlot {
+    fn name(&self) -> &'static str {
+        match self {
+            EquipSlot::Head => "Head",
+            EquipSlot::Suit => "Suit",
+            EquipSlot::Belt => "Belt",
+            EquipSlot::Back => "Back",
+            EquipSlot::Id => "ID",
+            EquipSlot::LeftPocket => "Left pocket",
+            EquipSlot::RightPocket => "Right pocket",
+        }
+    }
+}
+
+/// Worn in the slot it names, pockets and the id slot don't need this
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Wearable(pub EquipSlot);
+
+/// Holds other items, which leave the grid while they're inside
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Container {
+    pub capacity: usize,
+    pub contents: Vec<Entity>,
+}
+
+impl Container {
+    pub fn is_full(&self) -> bool {
+        self.contents.len() >= self.capacity
+    }
+}
+
+/// Where a carried item would land if dropped, following whoever holds, wears or stores it
+fn carried_location(world: &mut World, entity: Entity) -> Option<GridLocation> {
+    if let Some(location) = world.get::<GridLocation>(entity) {
+        return Some(location.clone());
+    }
+    let holder = world
+        .query::<(
+            Entity,
+            Option<&Hands>,
+            Option<&Inventory>,
+            Option<&Container>,
+        )>()
+        .iter(world)
+        .find(|(_, hands, inventory, container)| {
+            hands.is_some_and(|hands| hands.held().any(|held| held == entity))
+                || inventory.is_some_and(|inventory| inventory.worn().any(|worn| worn == entity))
+                || container.is_some_and(|container| container.contents.contains(&entity))
+        })
+        .map(|(holder, ..)| holder)?;
+    carried_location(world, holder)
+}
+
+/// Drops what was inside `container` where it is, or gets rid of it if it's nowhere at all
+pub fn spill_contents(world: &mut World, container: Entity, contents: Vec<Entity>) {
+    let location = carried_location(world, container);
+    for item in contents {
+        match &location {
+            Some(location) => {
+                world.entity_mut(item).insert((
+                    LockToGrid,
+                    location.clone(),
+                    Visibility::Inherited,
+                ));
+            }
+            None => despawn_with_children_recursive(world, item),
+        }
+    }
+}
+
+/// Everything worn besides what's in `Hands`
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub slots: Vec<(EquipSlot, Option<Entity>)>,
+}
+
+impl Inventory {
+    pub fn human() -> Self {
+        Self {
+            slots: [
+                EquipSlot::Head,
+                EquipSlot::Suit,
+                EquipSlot::Belt,
+                EquipSlot::Back,
+                EquipSlot::Id,
+                EquipSlot::LeftPocket,
+                EquipSlot::RightPocket,
+            ]
+            .into_iter()
+            .map(|slot| (slot, None))
+            .collect(),
+        }
+    }
+
+    pub fn get(&self, slot: EquipSlot) -> Option<Entity> {
+        self.slots
+            .iter()
+            .find(|(worn_slot, _)| *worn_slot == slot)
+            .and_then(|(_, worn)| *worn)
+    }
+
+    /// Takes the entity off whichever slot it's worn in
+    pub fn release(&mut self, entity: Entity) {
+        for (_, worn) in self.slots.iter_mut() {
+            if *worn == Some(entity) {
+                *worn = None;
+            }
+        }
+    }
+
+    /// Everything worn in any slot
+    pub fn worn(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.slots.iter().filter_map(|(_, worn)| *worn)
+    }
+}
+
+pub fn open_inventory(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    player: Query<(), (With<Player>, With<Inventory>)>,
+    mut open_menu: EventWriter<OpenMenu>,
+) {
+    if keyboard.just_pressed(KeyCode::I) && !player.is_empty() {
+        let menu = commands.spawn(InventoryMenu { selection: 0 }).id();
+        open_menu.send(OpenMenu(menu));
+    }
+}
+
+#[derive(Component)]
+pub struct InventoryMenu {
+    selection: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum InventoryRow {
+    Slot(usize),
+    /// Putting the active hand's item into the container
+    Container(Entity),
+    Stored(Entity, Entity),
+    Close,
+}
+
+/// Slots, then the contents of every container that's worn or held
+fn inventory_rows(
+    hands: &Hands,
+    inventory: &Inventory,
+    contents: impl Fn(Entity) -> Option<Vec<Entity>>,
+) -> Vec<InventoryRow> {
+    let mut rows = (0..inventory.slots.len())
+        .map(InventoryRow::Slot)
+        .collect::<Vec<_>>();
+    for carried in inventory.worn().chain(hands.held()) {
+        if let Some(stored) = contents(carried) {
+            rows.push(InventoryRow::Container(carried));
+            rows.extend(
+                stored
+                    .into_iter()
+                    .map(|item| InventoryRow::Stored(carried, item)),
+            );
+        }
+    }
+    rows.push(InventoryRow::Close);
+    rows
+}
+
+fn fits(
+    slot: EquipSlot,
+    item: Entity,
+    wearables: &Query<&Wearable>,
+    cards: &Query<&IdCard>,
+) -> bool {
+    match slot {
+        EquipSlot::Id => cards.contains(item),
+        EquipSlot::LeftPocket | EquipSlot::RightPocket => true,
+        slot => wearables.get(item).is_ok_and(|wearable| wearable.0 == slot),
+    }
+}
+
+/// Return swaps the selected row with the active hand, putting it away or taking it out
+#[allow(clippy::too_many_arguments)]
+pub fn inventory_menu(
+    mut commands: Commands,
+    menu: Res<CentralMenu>,
+    mut inventory_menus: Query<&mut InventoryMenu>,
+    mut player: Query<(&mut Hands, &mut Inventory, &mut Player)>,
+    mut containers: Query<&mut Container>,
+    wearables: Query<&Wearable>,
+    cards: Query<&IdCard>,
+    names: Query<&Name>,
+    input: Res<Input<KeyCode>>,
+    mut close_menu: EventWriter<CloseMenu>,
+    mut redraw_menu: EventWriter<MenuRedraw>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+) {
+    let Some(owner) = menu.owner else {
+        return;
+    };
+    let Ok(mut inventory_menu) = inventory_menus.get_mut(owner) else {
+        return;
+    };
+    let Ok((mut hands, mut inventory, mut player)) = player.get_single_mut() else {
+        return;
+    };
+    let contents = |containers: &Query<&mut Container>, entity| {
+        containers
+            .get(entity)
+            .ok()
+            .map(|container| container.contents.clone())
+    };
+    let rows = inventory_rows(&hands, &inventory, |entity| contents(&containers, entity));
+
+    if input.just_pressed(KeyCode::S) {
+        inventory_menu.selection = (inventory_menu.selection + 1).min(rows.len() - 1);
+        redraw_menu.send(MenuRedraw);
+    }
+    if input.just_pressed(KeyCode::W) {
+        inventory_menu.selection = inventory_menu.selection.saturating_sub(1);
+        redraw_menu.send(MenuRedraw);
+    }
+    if !input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or("item".to_string(), Name::to_string)
+    };
+    let held = hands.get_active_held();
+    let moved = match (rows[inventory_menu.selection], held) {
+        (InventoryRow::Close, _) => {
+            close_menu.send(CloseMenu);
+            commands.entity(owner).despawn_recursive();
+            return;
+        }
+        (InventoryRow::Slot(index), None) => {
+            let taken = inventory.slots[index].1.take();
+            if let Some(taken) = taken {
+                hands.pickup(taken);
+                commands.add(AddToLog(
+                    format!(
+                        "You take the {} out of your {} slot",
+                        name(taken),
+                        inventory.slots[index].0.name().to_lowercase()
+                    ),
+                    None,
+                ));
+            }
+            taken.is_some()
+        }
+        (InventoryRow::Slot(index), Some(held)) => {
+            let (slot, worn) = inventory.slots[index];
+            if worn.is_some() {
+                commands.add(AddToLog(
+                    format!("Your {} slot is already taken", slot.name().to_lowercase()),
+                    None,
+                ));
+                false
+            } else if !fits(slot, held, &wearables, &cards) {
+                commands.add(AddToLog(
+                    format!("The {} doesn't go there", name(held)),
+                    None,
+                ));
+                false
+            } else {
+                inventory.slots[index].1 = Some(held);
+                hands.clear_active();
+                commands.add(AddToLog(
+                    format!(
+                        "You put the {} in your {} slot",
+                        name(held),
+                        slot.name().to_lowercase()
+                    ),
+                    None,
+                ));
+                true
+            }
+        }
+        (InventoryRow::Container(_), None) => false,
+        (InventoryRow::Container(container), Some(held)) => {
+            // Nesting is left out so nothing can end up inside itself
+            let nested = containers.contains(held);
+            let Ok(mut storage) = containers.get_mut(container) else {
+                return;
+            };
+            if nested || storage.is_full() {
+                commands.add(AddToLog(
+                    format!("The {} won't fit in the {}", name(held), name(container)),
+                    None,
+                ));
+                false
+            } else {
+                storage.contents.push(held);
+                hands.clear_active();
+                commands.add(AddToLog(
+                    format!("You put the {} in the {}", name(held), name(container)),
+                    None,
+                ));
+                true
+            }
+        }
+        (InventoryRow::Stored(container, item), None) => {
+            let Ok(mut storage) = containers.get_mut(container) else {
+                return;
+            };
+            storage.contents.retain(|stored| *stored != item);
+            hands.pickup(item);
+            commands.add(AddToLog(
+                format!("You take the {} out of the {}", name(item), name(container)),
+                None,
+            ));
+            true
+        }
+        (InventoryRow::Stored(..), Some(_)) => {
+            commands.add(AddToLog("Your hand is full".to_string(), None));
+            false
+        }
+    };
+
+    if moved {
+        // Whatever left the hand can't be combined or aimed any more
+        if held.is_some() && player.combining == held {
+            player.combining = None;
+        }
+        if held.is_some() && player.targeting == held {
+            player.targeting = None;
+        }
+        turn_event.send(PlayerTookTurn(PICKUP_COST));
+        let rows = inventory_rows(&hands, &inventory, |entity| contents(&containers, entity));
+        inventory_menu.selection = inventory_menu.selection.min(rows.len() - 1);
+        redraw_menu.send(MenuRedraw);
+    }
+}
+
+pub fn update_inventory_menu_graphics(
+    mut commands: Commands,
+    mut menu: ResMut<CentralMenu>,
+    inventory_menus: Query<&InventoryMenu>,
+    player: Query<(&Hands, &Inventory), With<Player>>,
+    containers: Query<&Container>,
+    names: Query<&Name>,
+    mut event: EventReader<MenuRedraw>,
+) {
+    for _ev in event.read() {
+        let Some(owner) = menu.owner else {
+            continue;
+        };
+        let Ok(inventory_menu) = inventory_menus.get(owner) else {
+            continue;
+        };
+        let Ok((hands, inventory)) = player.get_single() else {
+            continue;
+        };
+        menu.clear_menu(&mut commands);
+        menu.set_row_text(&mut commands, "Inventory", 0, None);
+        let name = |entity: Entity| {
+            names
+                .get(entity)
+                .map_or("item".to_string(), Name::to_string)
+        };
+        let rows = inventory_rows(hands, inventory, |entity| {
+            containers
+                .get(entity)
+                .ok()
+                .map(|container| container.contents.clone())
+        });
+        for (i, row) in rows.into_iter().enumerate() {
+            let text = match row {
+                InventoryRow::Slot(index) => {
+                    let (slot, worn) = inventory.slots[index];
+                    format!("{}: {}", slot.name(), worn.map_or("-".to_string(), name))
+                }
+                InventoryRow::Container(container) => {
+                    let storage = containers.get(container).ok();
+                    format!(
+                        "{} ({}/{})",
+                        name(container),
+                        storage.map_or(0, |storage| storage.contents.len()),
+                        storage.map_or(0, |storage| storage.capacity)
+                    )
+                }
+                InventoryRow::Stored(_, item) => format!("  {}", name(item)),
+                InventoryRow::Close => "Close".to_string(),
+            };
+            let tint = (i == inventory_menu.selection).then_some(TintOverride(Color::YELLOW));
+            menu.set_row_text(&mut commands, &text, i + 1, tint);
+        }
+    }
+}
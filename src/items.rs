@@ -0,0 +1,249 @@
+use bevy::{ecs::system::Command, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access::IdCard,
+    fire::Flammable,
+    graphics::{GameSprite, TintOverride},
+    grid::LockToGrid,
+    inventory::{spill_contents, Container, EquipSlot, Wearable},
+    log::AddToLog,
+    ron_asset::{RonAsset, RonAssetLoader},
+    usuable::Lighter,
+    wiring::DiagramSheet,
+    Cigarette, Item, Tool,
+};
+
+pub const ITEM_DEFINITIONS_PATH: &str = "items/base.items.ron";
+
+/// Components an item definition can ask for, each maps onto the real component at spawn
+#[derive(Deserialize, Clone, Debug)]
+pub enum ItemComponent {
+    Tool(Tool),
+    Lighter,
+    Cigarette,
+    Flammable(Flammable),
+    WiringDiagram,
+    IdCard(IdCard),
+    Wearable(EquipSlot),
+    /// Holds up to this many items
+    Container(usize),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ItemDefinition {
+    pub id: String,
+    pub name: String,
+    pub glyph: char,
+    pub color: Color,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub components: Vec<ItemComponent>,
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct ItemDefinitions {
+    pub items: Vec<ItemDefinition>,
+}
+
+impl ItemDefinitions {
+    pub fn get(&self, id: &str) -> Option<&ItemDefinition> {
+        self.items.iter().find(|item| item.id == id)
+    }
+}
+
+/// The definition an item was spawned from, used to refresh it when the file changes
+#[derive(Component, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemId(pub String);
+
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ItemTags(pub Vec<String>);
+
+impl ItemTags {
+    pub fn has(&self, tag: &str) -> bool {
+        self.0.iter().any(|item_tag| item_tag == tag)
+    }
+}
+
+impl RonAsset for ItemDefinitions {
+    const EXTENSIONS: &'static [&'static str] = &["items.ron"];
+}
+
+#[derive(Resource)]
+pub struct ItemLibrary {
+    pub definitions: Handle<ItemDefinitions>,
+}
+
+impl FromWorld for ItemLibrary {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            definitions: world.resource::<AssetServer>().load(ITEM_DEFINITIONS_PATH),
+        }
+    }
+}
+
+pub fn items_loaded() -> impl Condition<()> {
+    IntoSystem::into_system(
+        |library: Res<ItemLibrary>, definitions: Res<Assets<ItemDefinitions>>| {
+            definitions.contains(&library.definitions)
+        },
+    )
+}
+
+/// Spawns an item from its definition id, into `entity` if one was reserved
+pub struct SpawnItem {
+    pub id: String,
+    pub entity: Option<Entity>,
+}
+
+impl Command for SpawnItem {
+    fn apply(self, world: &mut World) {
+        let library = world.resource::<ItemLibrary>();
+        let definition = world
+            .resource::<Assets<ItemDefinitions>>()
+            .get(&library.definitions)
+            .and_then(|definitions| definitions.get(&self.id))
+            .cloned();
+        let Some(definition) = definition else {
+            AddToLog(format!("No item called {}", self.id), None).apply(world);
+            return;
+        };
+
+        let mut entity = match self.entity {
+            Some(entity) => match world.get_entity_mut(entity) {
+                Some(entity) => entity,
+                None => return,
+            },
+            None => world.spawn_empty(),
+        };
+        entity.insert((
+            ItemId(definition.id),
+            Name::new(definition.name),
+            GameSprite::Text(definition.glyph),
+            TintOverride(definition.color),
+            ItemTags(definition.tags),
+            LockToGrid,
+            SpatialBundle::HIDDEN_IDENTITY,
+            Item,
+        ));
+        for component in definition.components {
+            match component {
+                ItemComponent::Tool(tool) => entity.insert(tool),
+                ItemComponent::Lighter => entity.insert(Lighter { active: false }),
+                ItemComponent::Cigarette => entity.insert(Cigarette),
+                ItemComponent::Flammable(flammable) => entity.insert(flammable),
+                ItemComponent::WiringDiagram => entity.insert(DiagramSheet),
+                ItemComponent::IdCard(card) => entity.insert(card),
+                ItemComponent::Wearable(slot) => entity.insert(Wearable(slot)),
+                ItemComponent::Container(capacity) => entity.insert(Container {
+                    capacity,
+                    contents: Vec::new(),
+                }),
+            };
+        }
+    }
+}
+
+/// Turns an existing item into another definition, keeping the entity so whoever holds it still does
+pub struct ReplaceItem {
+    pub entity: Entity,
+    pub id: String,
+}
+
+impl Command for ReplaceItem {
+    fn apply(self, world: &mut World) {
+        let Some(mut entity) = world.get_entity_mut(self.entity) else {
+            return;
+        };
+        // A lit lighter shows it in its tint, both go back on if it's still a lighter
+        let lit = entity
+            .get::<Lighter>()
+            .filter(|lighter| lighter.active)
+            .and(entity.get::<TintOverride>().cloned());
+        let mut stored = entity
+            .take::<Container>()
+            .map(|container| container.contents)
+            .unwrap_or_default();
+        entity.remove::<(
+            Tool,
+            Lighter,
+            Cigarette,
+            Flammable,
+            DiagramSheet,
+            IdCard,
+            Wearable,
+        )>();
+        // Spawning resets these for a fresh item, keep them for things already on the floor
+        let transform = entity.get::<Transform>().copied();
+        let visibility = entity.get::<Visibility>().copied();
+        SpawnItem {
+            id: self.id,
+            entity: Some(self.entity),
+        }
+        .apply(world);
+        let mut entity = world.entity_mut(self.entity);
+        if let Some(transform) = transform {
+            entity.insert(transform);
+        }
+        if let Some(visibility) = visibility {
+            entity.insert(visibility);
+        }
+        if let Some(tint) = lit.filter(|_| entity.contains::<Lighter>()) {
+            entity.insert((Lighter { active: true }, tint));
+        }
+        // Whatever no longer fits, or has nothing left to be in, falls out
+        let spilled = match entity.get_mut::<Container>() {
+            Some(mut container) => {
+                let spilled = stored.split_off(stored.len().min(container.capacity));
+                container.contents = stored;
+                spilled
+            }
+            None => stored,
+        };
+        spill_contents(world, self.entity, spilled);
+    }
+}
+
+/// Applies edited definitions to items already in the world, state like a lit lighter is kept
+fn reload_items(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<ItemDefinitions>>,
+    library: Res<ItemLibrary>,
+    definitions: Res<Assets<ItemDefinitions>>,
+    items: Query<(Entity, &ItemId)>,
+) {
+    // Everything is read so a burst of saves only reloads once
+    let modified = events
+        .read()
+        .filter(|event| event.is_modified(&library.definitions))
+        .count();
+    if modified == 0 {
+        return;
+    }
+    let Some(definitions) = definitions.get(&library.definitions) else {
+        return;
+    };
+
+    // Replacing an item with its own definition reapplies every component it lists
+    for (entity, id) in &items {
+        if definitions.get(&id.0).is_some() {
+            commands.add(ReplaceItem {
+                entity,
+                id: id.0.clone(),
+            });
+        }
+    }
+    commands.add(AddToLog("Reloaded item definitions".to_string(), None));
+}
+
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ItemDefinitions>()
+            .init_asset_loader::<RonAssetLoader<ItemDefinitions>>()
+            .init_resource::<ItemLibrary>()
+            .add_systems(Update, reload_items);
+    }
+}
@@ -0,0 +1,492 @@
+#![allow(clippy::type_complexity)]
+pub mod access;
+pub mod ai;
+pub mod atmos;
+pub mod door;
+pub mod fire;
+pub mod fov;
+pub mod graphics;
+pub mod grid;
+pub mod hands;
+pub mod headless;
+pub mod health;
+pub mod interactable;
+pub mod inventory;
+pub mod items;
+pub mod log;
+pub mod maintenance;
+pub mod map;
+mod menu;
+pub mod path;
+pub mod player;
+pub mod power;
+pub mod recipes;
+pub mod replay;
+mod ron_asset;
+pub mod save;
+pub mod status_bar;
+pub mod storage;
+mod text;
+pub mod turn;
+pub mod usuable;
+pub mod wfc;
+pub mod wiring;
+
+use access::RequiresAccess;
+use ai::{advance_routines, npc_behavior, Behavior, Routine};
+use atmos::{update_atmosphere, Atmosphere};
+use bevy::prelude::*;
+use bevy_turborand::{GlobalRng, RngComponent};
+use door::{close_doors, spawn_airlock, spawn_door, Door};
+use fire::{burn, clear_smoke, Flammable};
+use fov::{apply_fov, update_fov, FieldOfView, Opaque};
+use graphics::{GameSprite, Impassable, TintOverride};
+use grid::{Grid, GridLocation, GridPlugin, GridSystems, LockToGrid};
+use hands::{handle_give_item, GiveItem, Hands};
+use health::{
+    apply_damage, pass_incapacitated_turn, player_can_act, update_health, Damage, Health,
+};
+use interactable::{
+    player_interact, update_vending_machine_menu_graphics, vending_machine_menu, Interactable,
+    VendingMachine,
+};
+use inventory::{
+    inventory_menu, open_inventory, update_inventory_menu_graphics, Container, Inventory,
+};
+use items::{items_loaded, ItemTags, ItemsPlugin, SpawnItem};
+use log::Log;
+use maintenance::{Anchored, MaintenancePanel};
+use map::{load_map, LoadMap, MapTile};
+use menu::{menu_is_open, open_menu, CentralMenuPlugin, MenuRedraw};
+use path::follow_path;
+use player::{
+    drop_active_hand, move_player, pickup_from_ground, pickup_menu, start_combination,
+    update_active_hand, update_pickup_menu_graphics, use_active_hand, Player, PlayerCombined,
+    PlayerInteract, PlayerTookTurn, PlayerUsedOn,
+};
+use power::{update_lights, update_power, Cable, Light, PowerConsumer, PowerSource};
+use recipes::{combine_items, use_tool, RecipesPlugin};
+use save::save_load_input;
+use serde::{Deserialize, Serialize};
+use storage::{
+    spawn_storage, storage_menu, update_storage_menu_graphics, CRATE_CAPACITY, LOCKER_CAPACITY,
+};
+use turn::{
+    npcs_acting, player_ready, player_turn, run_scheduler, spend_player_energy, Energy,
+    TurnScheduler,
+};
+use usuable::{use_lighter, PlayerUsed};
+use wfc::{resolve_wfc, wfc, WfcSettings, WfcState};
+use wiring::{read_diagram, update_wiring_menu_graphics, wiring_menu, WiringDiagram};
+
+pub const SCREEN_TILE_SIZE_X: usize = 85;
+pub const SCREEN_TILE_SIZE_Y: usize = 48;
+// Screen tiles given to the game view, the map itself is scaled to fit
+pub const GAME_VIEW_TILE_SIZE_X: usize = 60;
+pub const GAME_VIEW_TILE_SIZE_Y: usize = 36;
+pub const TILE_SIZE: f32 = 9.0;
+pub const HUMAN_HEALTH: u32 = 100;
+const GENERATOR_OUTPUT: u32 = 10;
+const VENDING_MACHINE_DRAW: u32 = 5;
+const LIGHT_DRAW: u32 = 1;
+// Wall splitting the starting room in two
+pub const DIVIDER_X: u32 = 10;
+pub const SCREEN_SIZE_X: f32 = SCREEN_TILE_SIZE_X as f32 * TILE_SIZE;
+pub const SCREEN_SIZE_Y: f32 = SCREEN_TILE_SIZE_Y as f32 * TILE_SIZE;
+
+/// All of the simulation without a window or renderer, add it next to `DefaultPlugins` to play
+/// or `MinimalPlugins` to run it headless. Expects a `GlobalRng` from `RngPlugin`.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            GridPlugin::<Floor>::default(),
+            GridPlugin::<Item>::default(),
+            GridPlugin::<Impassable>::default(),
+            GridPlugin::<Interactable>::default(),
+            GridPlugin::<Opaque>::default(),
+            GridPlugin::<Flammable>::default(),
+            GridPlugin::<Cable>::default().connect_occupied(),
+            GridPlugin::<Door>::default(),
+            GridPlugin::<Health>::default(),
+            CentralMenuPlugin,
+            ItemsPlugin,
+            RecipesPlugin,
+        ))
+        .init_resource::<WfcSettings>()
+        .register_type::<WfcSettings>()
+        .init_resource::<WfcState>()
+        // Items are spawned from their definitions so the player waits for those to load
+        .add_systems(
+            Update,
+            spawn_player.run_if(items_loaded().and_then(run_once())),
+        )
+        .add_event::<PlayerTookTurn>()
+        .add_event::<PlayerInteract>()
+        .add_event::<PlayerUsedOn>()
+        .add_event::<GiveItem>()
+        .add_event::<PlayerUsed>()
+        .add_event::<PlayerCombined>()
+        .add_event::<LoadMap>()
+        .add_event::<Damage>()
+        .init_resource::<FieldOfView>()
+        .add_systems(
+            PostUpdate,
+            (update_fov, apply_fov).chain().after(GridSystems),
+        )
+        .init_resource::<Log>()
+        .init_resource::<TurnScheduler>()
+        .init_resource::<WiringDiagram>()
+        .init_resource::<Atmosphere>()
+        // please use schedules
+        .add_systems(
+            First,
+            (
+                vending_machine_menu.run_if(menu_is_open()),
+                pickup_menu.run_if(menu_is_open()),
+                wiring_menu.run_if(menu_is_open()),
+                inventory_menu.run_if(menu_is_open()),
+                storage_menu.run_if(menu_is_open()),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                update_active_hand.run_if(player_ready().and_then(player_can_act())),
+                handle_give_item,
+                pickup_from_ground.run_if(player_ready().and_then(player_can_act())),
+                use_lighter,
+                read_diagram,
+                drop_active_hand.run_if(player_ready().and_then(player_can_act())),
+                open_inventory.run_if(
+                    not(menu_is_open())
+                        .and_then(player_ready())
+                        .and_then(player_can_act()),
+                ),
+                start_combination.run_if(player_ready().and_then(player_can_act())),
+                combine_items,
+                pass_incapacitated_turn.run_if(player_ready()),
+                move_player.run_if(
+                    not(menu_is_open())
+                        .and_then(player_ready())
+                        .and_then(player_can_act()),
+                ),
+                use_tool,
+                use_active_hand.run_if(
+                    not(menu_is_open())
+                        .and_then(player_ready())
+                        .and_then(player_can_act()),
+                ),
+                // Only the one matching the open menu draws anything
+                (
+                    update_vending_machine_menu_graphics.run_if(on_event::<MenuRedraw>()),
+                    update_pickup_menu_graphics.run_if(on_event::<MenuRedraw>()),
+                    update_wiring_menu_graphics.run_if(on_event::<MenuRedraw>()),
+                    update_inventory_menu_graphics.run_if(on_event::<MenuRedraw>()),
+                    update_storage_menu_graphics.run_if(on_event::<MenuRedraw>()),
+                ),
+                // Not held back by a running generation, asking again replaces it
+                wfc.run_if(
+                    not(menu_is_open())
+                        .and_then(player_turn())
+                        .and_then(player_can_act()),
+                ),
+                resolve_wfc,
+                load_map,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (spend_player_energy, run_scheduler)
+                .chain()
+                .after(use_active_hand),
+        )
+        .add_systems(
+            Update,
+            (advance_routines, npc_behavior, apply_deferred, follow_path)
+                .chain()
+                .run_if(npcs_acting())
+                .after(run_scheduler),
+        )
+        .add_systems(
+            Update,
+            (
+                burn.run_if(npcs_acting()),
+                update_atmosphere,
+                apply_damage,
+                update_health,
+                clear_smoke,
+            )
+                .chain()
+                .after(run_scheduler),
+        )
+        .add_systems(Update, (update_power, update_lights).chain())
+        .add_systems(Update, close_doors.after(run_scheduler))
+        .add_systems(Update, save_load_input.run_if(not(menu_is_open())))
+        .add_systems(
+            Update,
+            player_interact
+                .run_if(on_event::<PlayerInteract>())
+                .before(move_player)
+                .before(open_menu),
+        );
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Floor;
+
+#[derive(Component, Debug, Default)]
+pub struct Item;
+
+#[derive(Component, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tool {
+    Screwdriver,
+    Wrench,
+    Crowbar,
+    Wirecutters,
+    Multitool,
+}
+
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
+pub struct Cigarette;
+
+#[derive(Component)]
+pub struct Npc;
+
+fn spawn_player(
+    mut commands: Commands,
+    mut global_rng: ResMut<GlobalRng>,
+    floor_grid: Res<Grid<Floor>>,
+) {
+    let diagram = WiringDiagram::generate(global_rng.as_mut());
+    let player = commands
+        .spawn((
+            Hands::human_hands(),
+            Inventory::human(),
+            GridLocation::new(1, 1),
+            LockToGrid,
+            RngComponent::from(&mut global_rng),
+            Impassable,
+            GameSprite::Player,
+            Player::default(),
+            Energy::default(),
+            Health::new(HUMAN_HEALTH),
+            ItemTags(vec!["human".to_string()]),
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, 600.0)),
+        ))
+        .id();
+    let spawn_items = |commands: &mut Commands, ids: &[&str]| {
+        ids.iter()
+            .map(|id| {
+                let entity = commands.spawn_empty().id();
+                commands.add(SpawnItem {
+                    id: id.to_string(),
+                    entity: Some(entity),
+                });
+                entity
+            })
+            .collect::<Vec<_>>()
+    };
+    let machine = spawn_items(
+        &mut commands,
+        &[
+            "screwdriver",
+            "lighter",
+            "cigarette",
+            "paper",
+            "wrench",
+            "crowbar",
+            "wirecutters",
+            "multitool",
+            "wiring_diagram",
+            "backpack",
+            "toolbelt",
+            "box",
+            "hard_hat",
+            "jumpsuit",
+        ],
+    );
+    let vending_machine = commands
+        .spawn((
+            MapTile,
+            GridLocation::new(2, 4),
+            LockToGrid,
+            Name::new("Vending machine"),
+            Interactable::VendingMachine,
+            ItemTags(vec!["machine".to_string()]),
+            MaintenancePanel::default(),
+            Anchored::default(),
+            diagram.wiring_for(&Interactable::VendingMachine),
+            PowerConsumer::new(VENDING_MACHINE_DRAW),
+            VendingMachine {
+                selection: 0,
+                options: machine,
+            },
+            Impassable,
+            GameSprite::VendingMachine,
+            SpatialBundle::default(),
+        ))
+        .id();
+    commands.insert_resource(diagram);
+    commands.spawn((
+        MapTile,
+        GridLocation::new(4, 4),
+        LockToGrid,
+        Name::new("Generator"),
+        PowerSource {
+            output: GENERATOR_OUTPUT,
+        },
+        Impassable,
+        GameSprite::Text('G'),
+        TintOverride(Color::ORANGE_RED),
+        SpatialBundle::default(),
+    ));
+    commands.spawn((
+        MapTile,
+        GridLocation::new(3, 5),
+        LockToGrid,
+        Name::new("Light"),
+        Light,
+        PowerConsumer::new(LIGHT_DRAW),
+        GameSprite::Text('*'),
+        TintOverride(Color::GRAY),
+        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -50.0)),
+    ));
+    // Runs from the generator under the vending machine and up to the light
+    commands.spawn_batch([(2, 4), (3, 4), (4, 4), (3, 5)].into_iter().map(|(x, y)| {
+        (
+            MapTile,
+            GridLocation::new(x, y),
+            LockToGrid,
+            Name::new("Cable"),
+            Cable,
+            ItemTags(vec!["cable".to_string()]),
+            GameSprite::Text('+'),
+            TintOverride(Color::MAROON),
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -90.0)),
+        )
+    }));
+    for x in 0..5 {
+        let routine = match x {
+            0 => Routine::new(vec![(Behavior::Follow(player), 30), (Behavior::Wander, 20)]),
+            1 => Routine::new(vec![
+                (Behavior::Wander, 15),
+                (Behavior::Use(vending_machine), 15),
+                (Behavior::Idle, 5),
+            ]),
+            _ => Routine::new(vec![(Behavior::Wander, 20), (Behavior::Idle, 5)]),
+        };
+        commands.spawn((
+            Hands::human_hands(),
+            Inventory::human(),
+            GridLocation::new(x + 2, 1),
+            Name::new("Crewmember"),
+            Npc,
+            Energy::default(),
+            Health::new(HUMAN_HEALTH),
+            ItemTags(vec!["human".to_string()]),
+            routine.current_behavior(),
+            routine,
+            LockToGrid,
+            RngComponent::from(&mut global_rng),
+            Impassable,
+            GameSprite::Npc,
+            SpatialBundle::default(),
+        ));
+    }
+    // Walled in so the air doesn't leak out past the edge of the map, with a second room
+    // through a door, a restricted door and an airlock
+    let size = floor_grid.size().as_ivec2();
+    let divider = DIVIDER_X as i32;
+    let (walls, floors): (Vec<_>, Vec<_>) =
+        floor_grid.all_points().into_iter().partition(|location| {
+            location.x == 0
+                || location.y == 0
+                || location.x == size.x - 1
+                || location.y == size.y - 1
+                || (location.x == divider && ![2, 6, 10].contains(&location.y))
+                || (location.x == divider + 1 && (location.y == 9 || location.y == 11))
+                || (location.x == divider + 2 && (9..=11).contains(&location.y) && location.y != 10)
+        });
+    let door = spawn_door(
+        &mut commands,
+        GridLocation::new(DIVIDER_X, 2),
+        "Door",
+        Door::default(),
+    );
+    let engineering_door = spawn_door(
+        &mut commands,
+        GridLocation::new(DIVIDER_X, 6),
+        "Engineering door",
+        Door::with_access("engineering"),
+    );
+    let airlock = spawn_airlock(
+        &mut commands,
+        GridLocation::new(DIVIDER_X, 10),
+        GridLocation::new(DIVIDER_X + 2, 10),
+    );
+    // A crate of odds and ends, and a locker only engineers can get into
+    let contents = spawn_items(&mut commands, &["paper", "paper", "box"]);
+    let supply_crate = spawn_storage(
+        &mut commands,
+        GridLocation::new(6, 5),
+        "Crate",
+        'C',
+        Container {
+            capacity: CRATE_CAPACITY,
+            contents,
+        },
+    );
+    let contents = spawn_items(&mut commands, &["toolbelt", "multitool", "hard_hat"]);
+    let locker = spawn_storage(
+        &mut commands,
+        GridLocation::new(DIVIDER_X + 2, 7),
+        "Engineering locker",
+        'L',
+        Container {
+            capacity: LOCKER_CAPACITY,
+            contents,
+        },
+    );
+    commands
+        .entity(locker)
+        .insert(RequiresAccess("engineering".to_string()));
+    // Fixtures go with the level when a new map is loaded
+    for fixture in [door, engineering_door, supply_crate, locker]
+        .into_iter()
+        .chain(airlock)
+    {
+        commands.entity(fixture).insert(MapTile);
+    }
+    // Someone left their card lying on the far side of the engineering door
+    let card = commands.spawn(GridLocation::new(DIVIDER_X + 2, 6)).id();
+    commands.add(SpawnItem {
+        id: "engineering_id".to_string(),
+        entity: Some(card),
+    });
+    commands.entity(card).insert(Visibility::Visible);
+    commands.spawn_batch(walls.into_iter().map(|location| {
+        (
+            MapTile,
+            LockToGrid,
+            location,
+            Impassable,
+            Opaque,
+            GameSprite::Wall,
+            SpatialBundle::default(),
+        )
+    }));
+    commands.spawn_batch(floors.into_iter().map(|location| {
+        (
+            MapTile,
+            LockToGrid,
+            location,
+            Floor,
+            GameSprite::Floor,
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -100.0)),
+        )
+    }));
+}
@@ -1,8 +1,10 @@
 use bevy::{ecs::system::Command, prelude::*};
 
-use crate::{graphics::TintOverride, grid::GRID_SIZE_X, SCREEN_TILE_SIZE_X, SCREEN_TILE_SIZE_Y};
+use crate::{
+    graphics::TintOverride, GAME_VIEW_TILE_SIZE_X, SCREEN_TILE_SIZE_X, SCREEN_TILE_SIZE_Y,
+};
 
-pub const LOG_SIZE_X: usize = SCREEN_TILE_SIZE_X - GRID_SIZE_X;
+pub const LOG_SIZE_X: usize = SCREEN_TILE_SIZE_X - GAME_VIEW_TILE_SIZE_X;
 pub const LOG_SIZE_Y: usize = SCREEN_TILE_SIZE_Y;
 
 #[derive(Resource, Default)]
@@ -0,0 +1,87 @@
+use bevy::{ecs::system::Command, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::log::AddToLog;
+
+/// Cover over a machine's insides, screwdrivers open it and once open the wiring can be reached
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
+pub struct MaintenancePanel {
+    pub open: bool,
+    /// Pried off with a crowbar, there's nothing left to screw shut
+    pub pried: bool,
+}
+
+/// Machines only work while bolted to the floor
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Anchored(pub bool);
+
+impl Default for Anchored {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+fn display_name(world: &World, entity: Entity) -> String {
+    world
+        .get::<Name>(entity)
+        .map_or("machine".to_string(), |name| name.to_lowercase())
+}
+
+pub struct TogglePanel(pub Entity);
+
+impl Command for TogglePanel {
+    fn apply(self, world: &mut World) {
+        let name = display_name(world, self.0);
+        let Some(mut panel) = world.get_mut::<MaintenancePanel>(self.0) else {
+            return;
+        };
+        let message = if panel.pried {
+            format!("The panel of the {} has been pried off", name)
+        } else {
+            panel.open = !panel.open;
+            if panel.open {
+                format!("You open the maintenance panel of the {}", name)
+            } else {
+                format!("You close the maintenance panel of the {}", name)
+            }
+        };
+        AddToLog(message, None).apply(world);
+    }
+}
+
+pub struct PryPanel(pub Entity);
+
+impl Command for PryPanel {
+    fn apply(self, world: &mut World) {
+        let name = display_name(world, self.0);
+        let Some(mut panel) = world.get_mut::<MaintenancePanel>(self.0) else {
+            return;
+        };
+        let message = if panel.open {
+            format!("The panel of the {} is already open", name)
+        } else {
+            panel.open = true;
+            panel.pried = true;
+            format!("You pry the panel off the {}", name)
+        };
+        AddToLog(message, None).apply(world);
+    }
+}
+
+pub struct ToggleAnchored(pub Entity);
+
+impl Command for ToggleAnchored {
+    fn apply(self, world: &mut World) {
+        let name = display_name(world, self.0);
+        let Some(mut anchored) = world.get_mut::<Anchored>(self.0) else {
+            return;
+        };
+        anchored.0 = !anchored.0;
+        let message = if anchored.0 {
+            format!("You bolt the {} to the floor", name)
+        } else {
+            format!("You unbolt the {} from the floor", name)
+        };
+        AddToLog(message, None).apply(world);
+    }
+}
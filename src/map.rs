@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use bevy_turborand::{DelegatedRng, RngComponent};
+
+use crate::{
+    atmos::Breach,
+    door::{spawn_door, Door},
+    fov::Opaque,
+    graphics::{GameSprite, Impassable, TintOverride},
+    grid::{GridLocation, LockToGrid, ResizeGrid},
+    interactable::VendingMachine,
+    inventory::Container,
+    log::AddToLog,
+    player::Player,
+    storage::{spawn_storage, CRATE_CAPACITY},
+    Floor, Item, Npc,
+};
+
+/// Entities with this component belong to the current level and are despawned when a new one loads
+#[derive(Component)]
+pub struct MapTile;
+
+/// Rows of map characters, top row first, as produced by the wfc generator
+#[derive(Event)]
+pub struct LoadMap(pub Vec<Vec<char>>);
+
+pub const WALL_CHAR: char = '#';
+pub const FLOOR_CHAR: char = '.';
+/// Floor with a hole in the hull, vents whatever air reaches it
+pub const BREACH_CHAR: char = 'O';
+pub const DOOR_CHAR: char = '+';
+/// Floor with an empty crate on it
+pub const CRATE_CHAR: char = 'C';
+
+/// Despawns the entity along with anything stocked or stored in it, which lives off the grid
+fn despawn_with_stored(
+    commands: &mut Commands,
+    entity: Entity,
+    machines: &Query<&VendingMachine>,
+    containers: &Query<&Container>,
+) {
+    let stock = machines
+        .get(entity)
+        .into_iter()
+        .flat_map(|machine| &machine.options);
+    let stored = containers
+        .get(entity)
+        .into_iter()
+        .flat_map(|container| &container.contents);
+    for item in stock.chain(stored) {
+        despawn_with_stored(commands, *item, machines, containers);
+    }
+    commands.entity(entity).despawn_recursive();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn load_map(
+    mut commands: Commands,
+    mut events: EventReader<LoadMap>,
+    mut resize: EventWriter<ResizeGrid>,
+    tiles: Query<Entity, With<MapTile>>,
+    loose_items: Query<Entity, (With<Item>, With<GridLocation>)>,
+    machines: Query<&VendingMachine>,
+    containers: Query<&Container>,
+    mut actors: Query<(&mut GridLocation, &mut RngComponent), Or<(With<Player>, With<Npc>)>>,
+) {
+    // Only the newest map matters if several were requested this frame
+    let Some(LoadMap(rows)) = events.read().last() else {
+        return;
+    };
+
+    // Whatever was left lying around or still stocked goes with the old level, carried items stay
+    for entity in tiles.iter().chain(&loose_items) {
+        despawn_with_stored(&mut commands, entity, &machines, &containers);
+    }
+
+    let height = rows.len();
+    let width = rows.iter().map(|line| line.len()).max().unwrap_or(0);
+    resize.send(ResizeGrid(UVec2::new(width as u32, height as u32)));
+
+    let mut open_floor = Vec::new();
+    for (row, line) in rows.iter().enumerate() {
+        // Text is written top down but the grid grows upwards
+        let y = (height - 1 - row) as u32;
+        for (x, character) in line.iter().enumerate() {
+            let location = GridLocation::new(x as u32, y);
+            match *character {
+                WALL_CHAR => {
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location,
+                        Impassable,
+                        Opaque,
+                        GameSprite::Wall,
+                        SpatialBundle::default(),
+                    ));
+                }
+                FLOOR_CHAR => {
+                    open_floor.push(location.clone());
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location,
+                        Floor,
+                        GameSprite::Floor,
+                        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -100.0)),
+                    ));
+                }
+                DOOR_CHAR => {
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location.clone(),
+                        Floor,
+                        GameSprite::Floor,
+                        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -100.0)),
+                    ));
+                    let door = spawn_door(&mut commands, location, "Door", Door::default());
+                    commands.entity(door).insert(MapTile);
+                }
+                CRATE_CHAR => {
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location.clone(),
+                        Floor,
+                        GameSprite::Floor,
+                        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -100.0)),
+                    ));
+                    let storage = spawn_storage(
+                        &mut commands,
+                        location,
+                        "Crate",
+                        'C',
+                        Container {
+                            capacity: CRATE_CAPACITY,
+                            contents: Vec::new(),
+                        },
+                    );
+                    commands.entity(storage).insert(MapTile);
+                }
+                BREACH_CHAR => {
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location.clone(),
+                        Floor,
+                        GameSprite::Floor,
+                        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -100.0)),
+                    ));
+                    commands.spawn((
+                        MapTile,
+                        LockToGrid,
+                        location,
+                        Breach,
+                        GameSprite::Text('O'),
+                        TintOverride(Color::MIDNIGHT_BLUE),
+                        SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, -90.0)),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Drop everyone somewhere they can actually stand
+    for (mut location, mut rng) in &mut actors {
+        if open_floor.is_empty() {
+            break;
+        }
+        let spot = open_floor.swap_remove(rng.usize(0..open_floor.len()));
+        // Grids are resized later this frame so validating against them here would use the old size
+        *location = spot;
+    }
+
+    commands.add(AddToLog("Loaded new level".to_string(), None));
+}
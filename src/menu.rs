@@ -2,19 +2,32 @@ use bevy::prelude::*;
 
 use crate::{
     graphics::{GameSprite, TintOverride},
-    grid::{GRID_SIZE_X, GRID_SIZE_Y},
+    grid::{Grid, DEFAULT_GRID_SIZE},
     text::{AsciiText, SpawnText},
-    TILE_SIZE,
+    Floor, TILE_SIZE,
 };
 
 pub const MENU_SIZE_X: usize = 48;
 pub const MENU_SIZE_Y: usize = 24;
 
-#[derive(Resource, Default)]
+#[derive(Resource)]
 pub struct CentralMenu {
     pub open: bool,
     pub contents: Vec<Entity>,
     pub owner: Option<Entity>,
+    // The menu is centered on the map, which is sized by the loaded level
+    pub map_size: UVec2,
+}
+
+impl Default for CentralMenu {
+    fn default() -> Self {
+        Self {
+            open: false,
+            contents: Vec::new(),
+            owner: None,
+            map_size: DEFAULT_GRID_SIZE,
+        }
+    }
 }
 
 impl CentralMenu {
@@ -42,8 +55,8 @@ impl CentralMenu {
             tint,
             entity: Some(entity),
             position: Vec3::new(
-                (GRID_SIZE_X - MENU_SIZE_X) as f32 / 2.0 * TILE_SIZE + TILE_SIZE * 0.5,
-                (GRID_SIZE_Y + MENU_SIZE_Y) as f32 / 2.0 * TILE_SIZE
+                (self.map_size.x as f32 - MENU_SIZE_X as f32) / 2.0 * TILE_SIZE + TILE_SIZE * 0.5,
+                (self.map_size.y as f32 + MENU_SIZE_Y as f32) / 2.0 * TILE_SIZE
                     - TILE_SIZE * 0.5
                     - row as f32 * TILE_SIZE,
                 900.0,
@@ -95,11 +108,12 @@ pub fn menu_is_open() -> impl Condition<()> {
     IntoSystem::into_system(|menu: Res<CentralMenu>| menu.open)
 }
 
-fn open_menu(
+pub fn open_menu(
     mut commands: Commands,
     mut events: EventReader<OpenMenu>,
     mut open_event: EventWriter<MenuRedraw>,
     mut menu: ResMut<CentralMenu>,
+    grid: Res<Grid<Floor>>,
 ) {
     for event in events.read() {
         if menu.open {
@@ -107,13 +121,14 @@ fn open_menu(
         }
         menu.open = true;
         menu.owner = Some(event.0);
+        menu.map_size = grid.size();
 
         commands.spawn((
             MenuItem,
             MenuBackground,
             SpatialBundle::from_transform(Transform::from_xyz(
-                (GRID_SIZE_X) as f32 / 2.0 * TILE_SIZE,
-                (GRID_SIZE_Y) as f32 / 2.0 * TILE_SIZE,
+                menu.map_size.x as f32 / 2.0 * TILE_SIZE,
+                menu.map_size.y as f32 / 2.0 * TILE_SIZE,
                 899.0,
             )),
             GameSprite::MenuBackground,
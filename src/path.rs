@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::{
+    access::{carried_access, IdCard},
+    door::{npc_passable, Door, OpenDoor},
+    graphics::Impassable,
+    grid::{DirtyGridEvent, Grid, GridLocation},
+    hands::Hands,
+    health::Incapacitated,
+    inventory::Inventory,
+    turn::TurnScheduler,
+};
+
+/// Walks the entity one tile per turn towards the destination, removed once it arrives
+#[derive(Component, Debug, Clone)]
+pub struct PathFollower {
+    pub destination: GridLocation,
+    pub diagonal: bool,
+    // Steps still to take, the next one is last so it pops off the end
+    path: Vec<GridLocation>,
+}
+
+impl PathFollower {
+    pub fn new(destination: GridLocation) -> Self {
+        Self {
+            destination,
+            diagonal: false,
+            path: Vec::new(),
+        }
+    }
+
+    /// Retargets the follower, keeping the planned path if nothing changed
+    pub fn set_destination(&mut self, destination: GridLocation) {
+        if self.destination != destination {
+            self.destination = destination;
+            self.path.clear();
+        }
+    }
+
+    pub fn with_diagonal(mut self) -> Self {
+        self.diagonal = true;
+        self
+    }
+}
+
+pub fn follow_path(
+    mut commands: Commands,
+    mut followers: Query<
+        (
+            Entity,
+            &mut GridLocation,
+            &mut PathFollower,
+            Option<&Hands>,
+            Option<&Inventory>,
+        ),
+        Without<Incapacitated>,
+    >,
+    mut grid: ResMut<Grid<Impassable>>,
+    doors: Query<&Door>,
+    cards: Query<&IdCard>,
+    scheduler: Res<TurnScheduler>,
+    mut dirty: EventWriter<DirtyGridEvent<Impassable>>,
+) {
+    for (entity, mut location, mut follower, hands, inventory) in &mut followers {
+        if !scheduler.is_acting(entity) {
+            continue;
+        }
+        if *location == follower.destination {
+            commands.entity(entity).remove::<PathFollower>();
+            continue;
+        }
+
+        let access = carried_access(hands, inventory, |held| cards.get(held).ok());
+        // Something moved into the way since the path was planned
+        let blocked = follower
+            .path
+            .last()
+            .map(|next| {
+                grid.occupied(next)
+                    && *next != follower.destination
+                    && npc_passable(&grid, &doors, next, &access).is_none()
+            })
+            .unwrap_or(true);
+        if blocked {
+            // Going through a door costs the turn spent opening it
+            let Some((path, _)) = grid.path_through(
+                &location,
+                &follower.destination,
+                follower.diagonal,
+                |point| npc_passable(&grid, &doors, point, &access).is_some(),
+                |point| {
+                    if grid.occupied(point) {
+                        2
+                    } else {
+                        1
+                    }
+                },
+            ) else {
+                // Unreachable for now, try again next turn
+                continue;
+            };
+            follower.path = path.into_iter().rev().collect();
+        }
+
+        let Some(next) = follower.path.last().cloned() else {
+            continue;
+        };
+        if let Some(door) = npc_passable(&grid, &doors, &next, &access) {
+            commands.add(OpenDoor {
+                door,
+                opener: entity,
+            });
+            continue;
+        }
+        // The destination itself may be occupied, stop next to it in that case
+        if grid.occupied(&next) {
+            commands.entity(entity).remove::<PathFollower>();
+            continue;
+        }
+        let previous = location.clone();
+        if location
+            .try_set_location(next.get_location(), &grid)
+            .is_ok()
+        {
+            follower.path.pop();
+            // Moved in the grid right away so followers later this turn see the tile taken, which
+            // leaves nothing for update_in_grid to notice
+            grid.force_update(entity, &next);
+            dirty.send(DirtyGridEvent::new(previous));
+            dirty.send(DirtyGridEvent::new(next));
+        }
+    }
+}
@@ -1,38 +1,57 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    door::{Door, OpenDoor},
     graphics::{Impassable, TintOverride},
-    grid::{Grid, GridLocation, LockToGrid},
+    grid::{DirtyGridEvent, Grid, GridLocation, LockToGrid},
     hands::{GiveItem, Hands},
     interactable::Interactable,
     log::AddToLog,
     menu::{CentralMenu, CloseMenu, MenuRedraw, OpenMenu},
+    turn::{INTERACT_COST, MOVE_COST, PICKUP_COST, USE_COST},
     usuable::PlayerUsed,
-    Item,
+    Item, Tool,
 };
 #[derive(Event)]
 pub struct PlayerCombined(pub Entity, pub Entity);
 
+/// The player did something that took this many ticks
 #[derive(Event)]
-pub struct PlayerTookTurn;
+pub struct PlayerTookTurn(pub u64);
 
 #[derive(Event)]
 pub struct PlayerInteract(pub GridLocation);
 
-#[derive(Component, Default)]
+/// A held tool was aimed at a neighboring tile
+#[derive(Event)]
+pub struct PlayerUsedOn {
+    pub tool: Entity,
+    pub location: GridLocation,
+}
+
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub combining: Option<Entity>,
+    /// Tool waiting for a direction to be used in
+    pub targeting: Option<Entity>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn move_player(
-    mut player: Query<(&mut GridLocation, &Player)>,
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut GridLocation, &mut Player)>,
     input: Res<Input<KeyCode>>,
-    wall_grid: Res<Grid<Impassable>>,
+    mut wall_grid: ResMut<Grid<Impassable>>,
     interact_grid: Res<Grid<Interactable>>,
+    door_grid: Res<Grid<Door>>,
+    doors: Query<&Door>,
     mut turn_event: EventWriter<PlayerTookTurn>,
     mut interact_event: EventWriter<PlayerInteract>,
+    mut used_on_event: EventWriter<PlayerUsedOn>,
+    mut dirty: EventWriter<DirtyGridEvent<Impassable>>,
 ) {
-    for (mut location, player) in &mut player {
+    for (entity, mut location, mut player) in &mut player {
         // TODO run if condition and allow player to combine with things on grid
         if player.combining.is_some() {
             return;
@@ -50,15 +69,48 @@ pub fn move_player(
             point.x -= 1;
         }
 
+        if let Some(tool) = player.targeting {
+            if point != location.get_location() {
+                used_on_event.send(PlayerUsedOn {
+                    tool,
+                    location: point.into(),
+                });
+                player.targeting = None;
+            }
+            return;
+        }
+
+        let previous = location.clone();
         if point != location.get_location()
             && !wall_grid.occupied(&point.into())
-            && location.try_set_location(point).is_ok()
+            && location.try_set_location(point, &wall_grid).is_ok()
         {
-            turn_event.send(PlayerTookTurn);
+            // Taken in the grid right away like npc steps, npcs acting later this frame would
+            // otherwise walk into the tile
+            if wall_grid.find_in_grid(entity).is_some() {
+                wall_grid.force_update(entity, &location);
+                dirty.send(DirtyGridEvent::new(previous));
+                dirty.send(DirtyGridEvent::new(location.clone()));
+            }
+            turn_event.send(PlayerTookTurn(MOVE_COST));
+            return;
+        }
+        // Bumping a closed door tries to open it
+        let closed_door = door_grid
+            .get(&point.into())
+            .iter()
+            .find(|door| doors.get(**door).is_ok_and(|door| !door.open))
+            .copied();
+        if let Some(door) = closed_door {
+            commands.add(OpenDoor {
+                door,
+                opener: entity,
+            });
+            turn_event.send(PlayerTookTurn(INTERACT_COST));
             return;
         }
         if point != location.get_location() && interact_grid.occupied(&point.into()) {
-            turn_event.send(PlayerTookTurn);
+            turn_event.send(PlayerTookTurn(INTERACT_COST));
             interact_event.send(PlayerInteract(point.into()));
             return;
         }
@@ -78,16 +130,33 @@ pub fn update_active_hand(
 }
 
 pub fn use_active_hand(
-    player: Query<&Hands, With<Player>>,
+    mut commands: Commands,
+    mut player: Query<(&Hands, &mut Player)>,
+    tools: Query<&Name, With<Tool>>,
     mut event: EventWriter<PlayerUsed>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
     keyboard: Res<Input<KeyCode>>,
 ) {
     if keyboard.just_pressed(KeyCode::Z) {
-        let Ok(hands) = player.get_single() else {
+        let Ok((hands, mut player)) = player.get_single_mut() else {
             return;
         };
+        if player.targeting.take().is_some() {
+            commands.add(AddToLog("Cancel".to_string(), None));
+            return;
+        }
         if let Some(entity) = hands.get_active_held() {
+            // Tools are used on something next to the player, the next direction picks what
+            if let Ok(name) = tools.get(entity) {
+                player.targeting = Some(entity);
+                commands.add(AddToLog(
+                    format!("Use the {} in which direction?", name.to_lowercase()),
+                    None,
+                ));
+                return;
+            }
             event.send(PlayerUsed(entity));
+            turn_event.send(PlayerTookTurn(USE_COST));
         }
     }
 }
@@ -106,8 +175,14 @@ pub fn drop_active_hand(
             if player.combining == Some(entity) {
                 player.combining = None;
             }
+            if player.targeting == Some(entity) {
+                player.targeting = None;
+            }
             commands.add(AddToLog("Dropping held item".to_string(), None));
-            commands.entity(entity).insert((LockToGrid, grid.clone())).insert(Visibility::Inherited);
+            commands
+                .entity(entity)
+                .insert((LockToGrid, grid.clone()))
+                .insert(Visibility::Inherited);
             hands.clear_active();
         }
     }
@@ -118,6 +193,7 @@ pub fn pickup_from_ground(
     player: Query<(&Hands, &GridLocation), With<Player>>,
     mut give_event: EventWriter<GiveItem>,
     mut menu_event: EventWriter<OpenMenu>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
     grid: Res<Grid<Item>>,
     keyboard: Res<Input<KeyCode>>,
 ) {
@@ -139,6 +215,7 @@ pub fn pickup_from_ground(
                     receiver: None,
                     item: entities[0],
                 });
+                turn_event.send(PlayerTookTurn(PICKUP_COST));
             } else {
                 let menu = commands
                     .spawn(PickupMenu {
@@ -167,6 +244,7 @@ pub fn pickup_menu(
     mut close_menu: EventWriter<CloseMenu>,
     mut redraw_menu: EventWriter<MenuRedraw>,
     mut give_item: EventWriter<GiveItem>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
     names: Query<&Name>,
 ) {
     if let Ok(mut pickup) = pickup.get_mut(menu.owner.unwrap()) {
@@ -184,6 +262,7 @@ pub fn pickup_menu(
                 receiver: None,
                 item: entity,
             });
+            turn_event.send(PlayerTookTurn(PICKUP_COST));
             close_menu.send(CloseMenu);
             commands.entity(menu.owner.unwrap()).despawn_recursive();
         }
@@ -240,7 +319,7 @@ pub fn start_combination(
                     commands.add(AddToLog("Cancel Combination".to_string(), None));
                     return;
                 }
-                commands.add(AddToLog("Combined with hand".to_string(), None));
+                // The recipe decides what it costs
                 event.send(PlayerCombined(first, second));
                 player.combining = None;
             } else {
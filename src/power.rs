@@ -0,0 +1,83 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::TintOverride,
+    grid::{ConnectedComponents, GridLocation},
+};
+
+const LIGHT_ON: Color = Color::rgb(1.0, 0.95, 0.6);
+const LIGHT_OFF: Color = Color::rgb(0.2, 0.2, 0.2);
+
+/// Cable laid on the floor, cables on touching tiles make up one network
+#[derive(Component, Default, Clone, Copy)]
+pub struct Cable;
+
+/// Generators and APCs, feeds whatever network has cable on its tile
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct PowerSource {
+    pub output: u32,
+}
+
+/// Needs `draw` from the network under it, a network asked for more than its sources
+/// put out browns out and powers nothing
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PowerConsumer {
+    pub draw: u32,
+    pub powered: bool,
+}
+
+impl PowerConsumer {
+    pub fn new(draw: u32) -> Self {
+        Self {
+            draw,
+            powered: false,
+        }
+    }
+}
+
+/// Lit while its consumer is powered
+#[derive(Component, Default, Clone, Copy)]
+pub struct Light;
+
+pub fn update_power(
+    networks: Res<ConnectedComponents<Cable>>,
+    sources: Query<(&PowerSource, &GridLocation)>,
+    mut consumers: Query<(&mut PowerConsumer, &GridLocation)>,
+) {
+    let mut supply = HashMap::<usize, u32>::default();
+    for (source, location) in &sources {
+        if let Some(network) = networks.component_id(location) {
+            *supply.entry(network).or_default() += source.output;
+        }
+    }
+    let mut demand = HashMap::<usize, u32>::default();
+    for (consumer, location) in &consumers {
+        if let Some(network) = networks.component_id(location) {
+            *demand.entry(network).or_default() += consumer.draw;
+        }
+    }
+
+    for (mut consumer, location) in &mut consumers {
+        let powered = networks.component_id(location).is_some_and(|network| {
+            supply.get(&network).copied().unwrap_or_default()
+                >= demand.get(&network).copied().unwrap_or_default()
+        });
+        // Only write on a change so lights can react to `Changed`
+        if consumer.powered != powered {
+            consumer.powered = powered;
+        }
+    }
+}
+
+pub fn update_lights(
+    mut lights: Query<(&PowerConsumer, &mut TintOverride), (With<Light>, Changed<PowerConsumer>)>,
+) {
+    for (consumer, mut tint) in &mut lights {
+        tint.0 = if consumer.powered {
+            LIGHT_ON
+        } else {
+            LIGHT_OFF
+        };
+    }
+}
@@ -0,0 +1,318 @@
+use bevy::{
+    ecs::{query::WorldQuery, system::Command},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    door::{Door, ToggleBolts},
+    fire::{Burning, Ignite},
+    graphics::TintOverride,
+    grid::{Grid, GridLocation},
+    hands::{GiveItem, Hands},
+    health::{Damage, DamageKind, Health},
+    interactable::Interactable,
+    items::{ItemTags, ReplaceItem, SpawnItem},
+    log::AddToLog,
+    maintenance::{Anchored, MaintenancePanel, PryPanel, ToggleAnchored, TogglePanel},
+    player::{Player, PlayerCombined, PlayerTookTurn, PlayerUsedOn},
+    power::Cable,
+    ron_asset::{RonAsset, RonAssetLoader},
+    turn::{COMBINE_COST, MOVE_COST},
+    usuable::Lighter,
+    wiring::OpenWiring,
+    Item,
+};
+
+pub const RECIPES_PATH: &str = "recipes/base.recipes.ron";
+
+/// Which side of a combination an effect applies to
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecipeSlot {
+    Tool,
+    Target,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum RecipeEffect {
+    Log(String),
+    Tint(RecipeSlot, Color),
+    Ignite(RecipeSlot),
+    /// Turns the item into another item definition
+    Transform(RecipeSlot, String),
+    /// Hands a new item to whoever combined, or drops it at their feet
+    Spawn(String),
+    Consume(RecipeSlot),
+    TogglePanel(RecipeSlot),
+    PryPanel(RecipeSlot),
+    ToggleAnchored(RecipeSlot),
+    OpenWiring(RecipeSlot),
+    ToggleBolts(RecipeSlot),
+    Damage(RecipeSlot, DamageKind, u32),
+}
+
+fn default_turns() -> u64 {
+    COMBINE_COST / MOVE_COST
+}
+
+/// Matches when the tool has every tag in `tool` and the target every tag in `target`
+#[derive(Deserialize, Clone, Debug)]
+pub struct Recipe {
+    pub name: String,
+    pub tool: Vec<String>,
+    pub target: Vec<String>,
+    #[serde(default)]
+    pub effects: Vec<RecipeEffect>,
+    #[serde(default = "default_turns")]
+    pub turns: u64,
+}
+
+impl Recipe {
+    fn matches(&self, tool: &ItemStateItem, target: &ItemStateItem) -> bool {
+        self.tool.iter().all(|tag| tool.has(tag)) && self.target.iter().all(|tag| target.has(tag))
+    }
+}
+
+/// Recipes are checked in file order and the first match wins, put specific ones first
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct RecipeBook {
+    pub recipes: Vec<Recipe>,
+}
+
+impl RonAsset for RecipeBook {
+    const EXTENSIONS: &'static [&'static str] = &["recipes.ron"];
+}
+
+impl RecipeBook {
+    fn find(&self, tool: &ItemStateItem, target: &ItemStateItem) -> Option<&Recipe> {
+        self.recipes
+            .iter()
+            .find(|recipe| recipe.matches(tool, target))
+    }
+}
+
+/// Static tags from the item definition plus ones that come from what the thing is doing,
+/// machines and other targets of tools are matched the same way
+#[derive(WorldQuery)]
+pub struct ItemState {
+    tags: Option<&'static ItemTags>,
+    lighter: Option<&'static Lighter>,
+    burning: Option<&'static Burning>,
+    panel: Option<&'static MaintenancePanel>,
+    anchored: Option<&'static Anchored>,
+}
+
+impl ItemStateItem<'_> {
+    pub fn has(&self, tag: &str) -> bool {
+        match tag {
+            "lit" => self.lighter.is_some_and(|lighter| lighter.active),
+            "burning" => self.burning.is_some(),
+            "panel_open" => self.panel.is_some_and(|panel| panel.open),
+            "anchored" => self.anchored.is_some_and(|anchored| anchored.0),
+            _ => self.tags.is_some_and(|tags| tags.has(tag)),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct RecipeLibrary {
+    pub book: Handle<RecipeBook>,
+}
+
+impl FromWorld for RecipeLibrary {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            book: world.resource::<AssetServer>().load(RECIPES_PATH),
+        }
+    }
+}
+
+pub fn combine_items(
+    mut commands: Commands,
+    mut events: EventReader<PlayerCombined>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+    library: Res<RecipeLibrary>,
+    books: Res<Assets<RecipeBook>>,
+    player: Query<Entity, With<Player>>,
+    items: Query<ItemState>,
+) {
+    for PlayerCombined(first, second) in events.read() {
+        let Ok(actor) = player.get_single() else {
+            return;
+        };
+        let (Ok(first_state), Ok(second_state)) = (items.get(*first), items.get(*second)) else {
+            continue;
+        };
+        let Some(book) = books.get(&library.book) else {
+            commands.add(AddToLog("Nothing happens".to_string(), None));
+            continue;
+        };
+
+        // Either hand can hold the tool
+        let found = book
+            .find(&first_state, &second_state)
+            .map(|recipe| (recipe, *first, *second))
+            .or_else(|| {
+                book.find(&second_state, &first_state)
+                    .map(|recipe| (recipe, *second, *first))
+            });
+        let Some((recipe, tool, target)) = found else {
+            commands.add(AddToLog("Nothing happens".to_string(), None));
+            continue;
+        };
+
+        debug!("Combining with recipe {}", recipe.name);
+        turn_event.send(PlayerTookTurn(recipe.turns * MOVE_COST));
+        commands.add(ApplyRecipe {
+            actor,
+            tool,
+            target,
+            effects: recipe.effects.clone(),
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn use_tool(
+    mut commands: Commands,
+    mut events: EventReader<PlayerUsedOn>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+    library: Res<RecipeLibrary>,
+    books: Res<Assets<RecipeBook>>,
+    player: Query<(Entity, &Hands), With<Player>>,
+    creature_grid: Res<Grid<Health>>,
+    interactable_grid: Res<Grid<Interactable>>,
+    door_grid: Res<Grid<Door>>,
+    item_grid: Res<Grid<Item>>,
+    cable_grid: Res<Grid<Cable>>,
+    states: Query<ItemState>,
+) {
+    for PlayerUsedOn { tool, location } in events.read() {
+        let Ok((actor, hands)) = player.get_single() else {
+            return;
+        };
+        // Swapped away or dropped while picking a direction
+        if !hands.hands.iter().any(|hand| hand.holding == Some(*tool)) {
+            continue;
+        }
+        // Whoever is standing there comes first, then machines over whatever is lying in front
+        // of them, cable is underneath it all
+        let Some(target) = creature_grid
+            .get(location)
+            .first()
+            .or(interactable_grid.get(location).first())
+            .or(door_grid.get(location).first())
+            .or(item_grid.get(location).first())
+            .or(cable_grid.get(location).first())
+            .copied()
+        else {
+            commands.add(AddToLog("There's nothing there".to_string(), None));
+            continue;
+        };
+        let (Ok(tool_state), Ok(target_state)) = (states.get(*tool), states.get(target)) else {
+            continue;
+        };
+        let Some(recipe) = books
+            .get(&library.book)
+            .and_then(|book| book.find(&tool_state, &target_state))
+        else {
+            commands.add(AddToLog("Nothing happens".to_string(), None));
+            continue;
+        };
+
+        debug!("Using tool with recipe {}", recipe.name);
+        turn_event.send(PlayerTookTurn(recipe.turns * MOVE_COST));
+        commands.add(ApplyRecipe {
+            actor,
+            tool: *tool,
+            target,
+            effects: recipe.effects.clone(),
+        });
+    }
+}
+
+pub struct ApplyRecipe {
+    pub actor: Entity,
+    pub tool: Entity,
+    pub target: Entity,
+    pub effects: Vec<RecipeEffect>,
+}
+
+impl ApplyRecipe {
+    fn slot(&self, slot: RecipeSlot) -> Entity {
+        match slot {
+            RecipeSlot::Tool => self.tool,
+            RecipeSlot::Target => self.target,
+        }
+    }
+}
+
+impl Command for ApplyRecipe {
+    fn apply(self, world: &mut World) {
+        for effect in self.effects.iter().cloned() {
+            match effect {
+                RecipeEffect::Log(message) => AddToLog(message, None).apply(world),
+                RecipeEffect::Tint(slot, color) => {
+                    if let Some(mut entity) = world.get_entity_mut(self.slot(slot)) {
+                        entity.insert(TintOverride(color));
+                    }
+                }
+                RecipeEffect::Ignite(slot) => Ignite(self.slot(slot)).apply(world),
+                RecipeEffect::Transform(slot, id) => ReplaceItem {
+                    entity: self.slot(slot),
+                    id,
+                }
+                .apply(world),
+                RecipeEffect::Spawn(id) => {
+                    let item = world.spawn_empty().id();
+                    SpawnItem {
+                        id,
+                        entity: Some(item),
+                    }
+                    .apply(world);
+                    // Lands on the floor if there's no free hand to take it
+                    if let Some(location) = world.get::<GridLocation>(self.actor).cloned() {
+                        world
+                            .entity_mut(item)
+                            .insert((location, Visibility::Visible));
+                    }
+                    world.send_event(GiveItem {
+                        receiver: Some(self.actor),
+                        item,
+                    });
+                }
+                RecipeEffect::TogglePanel(slot) => TogglePanel(self.slot(slot)).apply(world),
+                RecipeEffect::PryPanel(slot) => PryPanel(self.slot(slot)).apply(world),
+                RecipeEffect::ToggleAnchored(slot) => ToggleAnchored(self.slot(slot)).apply(world),
+                RecipeEffect::OpenWiring(slot) => OpenWiring(self.slot(slot)).apply(world),
+                RecipeEffect::ToggleBolts(slot) => ToggleBolts(self.slot(slot)).apply(world),
+                RecipeEffect::Damage(slot, kind, amount) => {
+                    world.send_event(Damage {
+                        target: self.slot(slot),
+                        kind,
+                        amount,
+                    });
+                }
+                RecipeEffect::Consume(slot) => {
+                    let item = self.slot(slot);
+                    if let Some(mut hands) = world.get_mut::<Hands>(self.actor) {
+                        hands.release(item);
+                    }
+                    if let Some(entity) = world.get_entity_mut(item) {
+                        entity.despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct RecipesPlugin;
+
+impl Plugin for RecipesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<RecipeBook>()
+            .init_asset_loader::<RonAssetLoader<RecipeBook>>()
+            .init_resource::<RecipeLibrary>();
+    }
+}
@@ -0,0 +1,262 @@
+use std::fs;
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{health::player_can_act, menu::menu_is_open, turn::player_turn, wfc::WfcState};
+
+pub const REPLAY_VERSION: u32 = 1;
+pub const DEFAULT_SEED: u64 = 0;
+// Frames between played back actions so menus and turn events settle like they did live
+const PLAYBACK_FRAME_DELAY: u32 = 4;
+
+/// Everything the player can do from the keyboard that affects the run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayerAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pickup,
+    Drop,
+    Use,
+    SwapHand,
+    Combine,
+    Confirm,
+    Inventory,
+    /// Generates a new level from the run's rng
+    Generate,
+}
+
+impl PlayerAction {
+    const ALL: [PlayerAction; 12] = [
+        PlayerAction::Up,
+        PlayerAction::Down,
+        PlayerAction::Left,
+        PlayerAction::Right,
+        PlayerAction::Pickup,
+        PlayerAction::Drop,
+        PlayerAction::Use,
+        PlayerAction::SwapHand,
+        PlayerAction::Combine,
+        PlayerAction::Confirm,
+        PlayerAction::Inventory,
+        PlayerAction::Generate,
+    ];
+
+    pub fn key(&self) -> KeyCode {
+        match self {
+            PlayerAction::Up => KeyCode::W,
+            PlayerAction::Down => KeyCode::S,
+            PlayerAction::Left => KeyCode::A,
+            PlayerAction::Right => KeyCode::D,
+            PlayerAction::Pickup => KeyCode::G,
+            PlayerAction::Drop => KeyCode::Q,
+            PlayerAction::Use => KeyCode::Z,
+            PlayerAction::SwapHand => KeyCode::X,
+            PlayerAction::Combine => KeyCode::C,
+            PlayerAction::Confirm => KeyCode::Return,
+            PlayerAction::Inventory => KeyCode::I,
+            PlayerAction::Generate => KeyCode::Space,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub actions: Vec<PlayerAction>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let replay: Replay = ron::from_str(&text).map_err(|err| err.to_string())?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay is version {}, expected {}",
+                replay.version, REPLAY_VERSION
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Resource, Clone)]
+pub enum ReplayMode {
+    Recording {
+        path: String,
+        replay: Replay,
+    },
+    Playback {
+        replay: Replay,
+        next: usize,
+        wait: u32,
+    },
+}
+
+impl ReplayMode {
+    /// Every recorded action has been played back, recordings never finish
+    pub fn finished(&self) -> bool {
+        match self {
+            ReplayMode::Recording { .. } => false,
+            ReplayMode::Playback { replay, next, .. } => *next >= replay.actions.len(),
+        }
+    }
+}
+
+/// Records with `--record <path>` or plays back with `--replay <path>`, `--seed <seed>` picks the
+/// seed of a recorded or unrecorded run
+pub struct ReplayPlugin {
+    mode: Option<ReplayMode>,
+    seed: u64,
+}
+
+impl ReplayPlugin {
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let args = args.collect::<Vec<_>>();
+        let flag = |name: &str| {
+            args.iter()
+                .position(|arg| arg == name)
+                .and_then(|index| args.get(index + 1))
+                .cloned()
+        };
+
+        let seed = flag("--seed")
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(DEFAULT_SEED);
+
+        let mode = if let Some(path) = flag("--replay") {
+            match Replay::load(&path) {
+                Ok(replay) => Some(ReplayMode::Playback {
+                    replay,
+                    next: 0,
+                    wait: 0,
+                }),
+                Err(err) => {
+                    // Logging isn't up yet while the app is being built
+                    eprintln!("Could not load replay {}: {}", path, err);
+                    None
+                }
+            }
+        } else {
+            flag("--record").map(|path| ReplayMode::Recording {
+                path,
+                replay: Replay {
+                    version: REPLAY_VERSION,
+                    seed,
+                    actions: Vec::new(),
+                },
+            })
+        };
+        Self { mode, seed }
+    }
+
+    pub fn playback(replay: Replay) -> Self {
+        Self {
+            seed: replay.seed,
+            mode: Some(ReplayMode::Playback {
+                replay,
+                next: 0,
+                wait: 0,
+            }),
+        }
+    }
+
+    /// The seed the run must use so playback matches the recording
+    pub fn seed(&self) -> u64 {
+        match &self.mode {
+            Some(ReplayMode::Recording { replay, .. })
+            | Some(ReplayMode::Playback { replay, .. }) => replay.seed,
+            None => self.seed,
+        }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(mode) = &self.mode else {
+            return;
+        };
+        // Keys only count when the game would act on them, so recording and playback agree on
+        // which presses did anything. While a level generates that's only asking for another one
+        app.insert_resource(mode.clone()).add_systems(
+            PreUpdate,
+            (
+                ignore_keyboard,
+                (record_actions, play_actions)
+                    .run_if(menu_is_open().or_else(player_turn().and_then(player_can_act()))),
+            )
+                .chain()
+                .after(InputSystem),
+        );
+    }
+}
+
+/// Only asking for another level does anything while one generates
+fn acted_on(action: PlayerAction, wfc: &WfcState) -> bool {
+    !wfc.running() || action == PlayerAction::Generate
+}
+
+fn record_actions(mut mode: ResMut<ReplayMode>, input: Res<Input<KeyCode>>, wfc: Res<WfcState>) {
+    let ReplayMode::Recording { path, replay } = mode.as_mut() else {
+        return;
+    };
+    let pressed = PlayerAction::ALL
+        .into_iter()
+        .filter(|action| input.just_pressed(action.key()) && acted_on(*action, &wfc))
+        .collect::<Vec<_>>();
+    if pressed.is_empty() {
+        return;
+    }
+    replay.actions.extend(pressed);
+    // Written every time so a crash still leaves the replay that caused it
+    if let Err(err) = replay.write(path) {
+        error!("Could not write replay {}: {}", path, err);
+    }
+}
+
+/// The real keyboard is ignored while playing back, this also lets go of the last played key
+fn ignore_keyboard(mode: Res<ReplayMode>, mut input: ResMut<Input<KeyCode>>) {
+    if !matches!(mode.as_ref(), ReplayMode::Playback { .. }) {
+        return;
+    }
+    for action in PlayerAction::ALL {
+        input.reset(action.key());
+    }
+}
+
+fn play_actions(
+    mut mode: ResMut<ReplayMode>,
+    mut input: ResMut<Input<KeyCode>>,
+    wfc: Res<WfcState>,
+) {
+    let ReplayMode::Playback { replay, next, wait } = mode.as_mut() else {
+        return;
+    };
+
+    if *wait > 0 {
+        *wait -= 1;
+        return;
+    }
+    let Some(&action) = replay.actions.get(*next) else {
+        return;
+    };
+    if !acted_on(action, &wfc) {
+        return;
+    }
+
+    input.press(action.key());
+    *next += 1;
+    *wait = PLAYBACK_FRAME_DELAY;
+    if *next == replay.actions.len() {
+        info!("Replay finished");
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fmt, marker::PhantomData};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, BoxedFuture, LoadContext},
+    prelude::*,
+};
+use serde::Deserialize;
+
+/// Game data written by hand in ron, each kind gets its own double extension like `items.ron`
+pub trait RonAsset: Asset + for<'de> Deserialize<'de> {
+    const EXTENSIONS: &'static [&'static str];
+}
+
+#[derive(Debug)]
+pub enum RonLoadError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for RonLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonLoadError::Io(err) => write!(f, "could not read file: {}", err),
+            RonLoadError::Ron(err) => write!(f, "could not parse file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RonLoadError {}
+
+pub struct RonAssetLoader<A>(PhantomData<A>);
+
+impl<A> Default for RonAssetLoader<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: RonAsset> AssetLoader for RonAssetLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<A, RonLoadError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(RonLoadError::Io)?;
+            ron::de::from_bytes(&bytes).map_err(RonLoadError::Ron)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        A::EXTENSIONS
+    }
+}
@@ -0,0 +1,485 @@
+use std::{collections::HashMap, fs};
+
+use bevy::{ecs::system::Command, hierarchy::despawn_with_children_recursive, prelude::*};
+use bevy_turborand::{GlobalRng, RngComponent};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    access::{IdCard, RequiresAccess},
+    ai::{Behavior, Routine},
+    atmos::{Atmosphere, Breach},
+    door::{Airlock, Door},
+    fire::{Burning, Flammable, Smoke},
+    fov::Opaque,
+    graphics::{GameSprite, Impassable, TintOverride},
+    grid::{Grid, GridLocation, LockToGrid, ResizeGrid},
+    hands::Hands,
+    health::Health,
+    interactable::{Interactable, VendingMachine},
+    inventory::{Container, Inventory, Wearable},
+    items::{ItemId, ItemTags},
+    log::{AddToLog, Log},
+    maintenance::{Anchored, MaintenancePanel},
+    map::MapTile,
+    player::Player,
+    power::{Cable, Light, PowerConsumer, PowerSource},
+    storage::Storage,
+    turn::{Energy, TurnScheduler},
+    usuable::Lighter,
+    wiring::{DiagramSheet, Wiring, WiringDiagram},
+    Cigarette, Floor, Item, Npc, Tool,
+};
+
+/// Bump whenever the save format changes, older saves are refused rather than half loaded
+pub const SAVE_VERSION: u32 = 12;
+pub const SAVE_PATH: &str = "save.ron";
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub map_size: UVec2,
+    pub clock: u64,
+    pub log: Vec<String>,
+    pub global_rng: GlobalRng,
+    pub wiring_diagram: WiringDiagram,
+    pub atmosphere: Atmosphere,
+    pub entities: Vec<SavedEntity>,
+}
+
+/// Unit components, stored by name so new ones don't change the layout
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Npc,
+    Item,
+    Floor,
+    Impassable,
+    Opaque,
+    MapTile,
+    LockToGrid,
+    DiagramSheet,
+    Cable,
+    Light,
+    Breach,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntity {
+    /// Id when saved, only used to remap references between saved entities
+    pub id: Entity,
+    pub markers: Vec<Marker>,
+    pub depth: f32,
+    pub hidden: bool,
+    pub name: Option<String>,
+    pub location: Option<GridLocation>,
+    pub sprite: Option<GameSprite>,
+    pub tint: Option<TintOverride>,
+    pub item_id: Option<ItemId>,
+    pub tags: Option<ItemTags>,
+    pub hands: Option<Hands>,
+    pub player: Option<Player>,
+    pub interactable: Option<Interactable>,
+    pub vending_machine: Option<VendingMachine>,
+    pub tool: Option<Tool>,
+    pub lighter: Option<Lighter>,
+    pub cigarette: Option<Cigarette>,
+    pub rng: Option<RngComponent>,
+    pub energy: Option<Energy>,
+    pub behavior: Option<Behavior>,
+    pub routine: Option<Routine>,
+    pub flammable: Option<Flammable>,
+    pub burning: Option<Burning>,
+    pub smoke: Option<Smoke>,
+    pub health: Option<Health>,
+    pub panel: Option<MaintenancePanel>,
+    pub anchored: Option<Anchored>,
+    pub wiring: Option<Wiring>,
+    pub power_source: Option<PowerSource>,
+    pub power_consumer: Option<PowerConsumer>,
+    pub door: Option<Door>,
+    pub airlock: Option<Airlock>,
+    pub id_card: Option<IdCard>,
+    pub requires_access: Option<RequiresAccess>,
+    pub inventory: Option<Inventory>,
+    pub wearable: Option<Wearable>,
+    pub container: Option<Container>,
+    pub storage: Option<Storage>,
+}
+
+/// Points entity references at the entities spawned for this load, dropping ones that weren't saved
+pub trait RemapEntities {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>);
+}
+
+impl RemapEntities for Hands {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        for hand in self.hands.iter_mut() {
+            hand.holding = hand.holding.and_then(|held| mapping.get(&held).copied());
+        }
+    }
+}
+
+impl RemapEntities for Inventory {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        for (_, worn) in self.slots.iter_mut() {
+            *worn = worn.and_then(|worn| mapping.get(&worn).copied());
+        }
+    }
+}
+
+impl RemapEntities for Container {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        self.contents = self
+            .contents
+            .iter()
+            .filter_map(|stored| mapping.get(stored).copied())
+            .collect();
+    }
+}
+
+impl RemapEntities for VendingMachine {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        self.options = self
+            .options
+            .iter()
+            .filter_map(|option| mapping.get(option).copied())
+            .collect();
+        self.selection = 0;
+    }
+}
+
+impl RemapEntities for Player {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        self.combining = self
+            .combining
+            .and_then(|combining| mapping.get(&combining).copied());
+        self.targeting = self
+            .targeting
+            .and_then(|targeting| mapping.get(&targeting).copied());
+    }
+}
+
+impl RemapEntities for Airlock {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        // The pair is always saved together, a missing partner just means a lone door
+        if let Some(partner) = mapping.get(&self.partner) {
+            self.partner = *partner;
+        }
+    }
+}
+
+impl RemapEntities for Behavior {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        let target = match self {
+            Behavior::Flee(target) | Behavior::Follow(target) | Behavior::Use(target) => target,
+            _ => return,
+        };
+        match mapping.get(target) {
+            Some(mapped) => *target = *mapped,
+            None => *self = Behavior::Idle,
+        }
+    }
+}
+
+impl RemapEntities for Routine {
+    fn remap_entities(&mut self, mapping: &HashMap<Entity, Entity>) {
+        for (behavior, _) in self.steps.iter_mut() {
+            behavior.remap_entities(mapping);
+        }
+    }
+}
+
+fn remapped<T: RemapEntities>(mut value: T, mapping: &HashMap<Entity, Entity>) -> T {
+    value.remap_entities(mapping);
+    value
+}
+
+/// Everything that makes up a run: the map, actors and items, wherever they are held
+fn saved_entities(world: &mut World) -> Vec<Entity> {
+    world
+        .query_filtered::<Entity, (With<GameSprite>, Or<(With<GridLocation>, With<Item>)>)>()
+        .iter(world)
+        .collect()
+}
+
+impl SavedEntity {
+    fn from_world(world: &World, entity: Entity) -> Self {
+        let entity_ref = world.entity(entity);
+        let markers = [
+            (Marker::Npc, entity_ref.contains::<Npc>()),
+            (Marker::Item, entity_ref.contains::<Item>()),
+            (Marker::Floor, entity_ref.contains::<Floor>()),
+            (Marker::Impassable, entity_ref.contains::<Impassable>()),
+            (Marker::Opaque, entity_ref.contains::<Opaque>()),
+            (Marker::MapTile, entity_ref.contains::<MapTile>()),
+            (Marker::LockToGrid, entity_ref.contains::<LockToGrid>()),
+            (Marker::DiagramSheet, entity_ref.contains::<DiagramSheet>()),
+            (Marker::Cable, entity_ref.contains::<Cable>()),
+            (Marker::Light, entity_ref.contains::<Light>()),
+            (Marker::Breach, entity_ref.contains::<Breach>()),
+        ]
+        .into_iter()
+        .filter_map(|(marker, present)| present.then_some(marker))
+        .collect();
+
+        Self {
+            id: entity,
+            markers,
+            depth: entity_ref
+                .get::<Transform>()
+                .map_or(0.0, |transform| transform.translation.z),
+            hidden: entity_ref.get::<Visibility>() == Some(&Visibility::Hidden),
+            name: entity_ref.get::<Name>().map(|name| name.to_string()),
+            location: entity_ref.get::<GridLocation>().cloned(),
+            sprite: entity_ref.get::<GameSprite>().cloned(),
+            tint: entity_ref.get::<TintOverride>().cloned(),
+            item_id: entity_ref.get::<ItemId>().cloned(),
+            tags: entity_ref.get::<ItemTags>().cloned(),
+            hands: entity_ref.get::<Hands>().cloned(),
+            player: entity_ref.get::<Player>().cloned(),
+            interactable: entity_ref.get::<Interactable>().cloned(),
+            vending_machine: entity_ref.get::<VendingMachine>().cloned(),
+            tool: entity_ref.get::<Tool>().cloned(),
+            lighter: entity_ref.get::<Lighter>().cloned(),
+            cigarette: entity_ref.get::<Cigarette>().cloned(),
+            rng: entity_ref.get::<RngComponent>().cloned(),
+            energy: entity_ref.get::<Energy>().cloned(),
+            behavior: entity_ref.get::<Behavior>().cloned(),
+            routine: entity_ref.get::<Routine>().cloned(),
+            flammable: entity_ref.get::<Flammable>().cloned(),
+            burning: entity_ref.get::<Burning>().cloned(),
+            smoke: entity_ref.get::<Smoke>().cloned(),
+            health: entity_ref.get::<Health>().cloned(),
+            panel: entity_ref.get::<MaintenancePanel>().cloned(),
+            anchored: entity_ref.get::<Anchored>().cloned(),
+            wiring: entity_ref.get::<Wiring>().cloned(),
+            power_source: entity_ref.get::<PowerSource>().cloned(),
+            power_consumer: entity_ref.get::<PowerConsumer>().cloned(),
+            door: entity_ref.get::<Door>().cloned(),
+            airlock: entity_ref.get::<Airlock>().cloned(),
+            id_card: entity_ref.get::<IdCard>().cloned(),
+            requires_access: entity_ref.get::<RequiresAccess>().cloned(),
+            inventory: entity_ref.get::<Inventory>().cloned(),
+            wearable: entity_ref.get::<Wearable>().copied(),
+            container: entity_ref.get::<Container>().cloned(),
+            storage: entity_ref.get::<Storage>().cloned(),
+        }
+    }
+
+    fn spawn(self, world: &mut World, mapping: &HashMap<Entity, Entity>) {
+        let mut entity = world.entity_mut(mapping[&self.id]);
+        entity.insert(SpatialBundle {
+            transform: Transform::from_xyz(0.0, 0.0, self.depth),
+            visibility: if self.hidden {
+                Visibility::Hidden
+            } else {
+                Visibility::Inherited
+            },
+            ..default()
+        });
+        for marker in self.markers {
+            match marker {
+                Marker::Npc => entity.insert(Npc),
+                Marker::Item => entity.insert(Item),
+                Marker::Floor => entity.insert(Floor),
+                Marker::Impassable => entity.insert(Impassable),
+                Marker::Opaque => entity.insert(Opaque),
+                Marker::MapTile => entity.insert(MapTile),
+                Marker::LockToGrid => entity.insert(LockToGrid),
+                Marker::DiagramSheet => entity.insert(DiagramSheet),
+                Marker::Cable => entity.insert(Cable),
+                Marker::Light => entity.insert(Light),
+                Marker::Breach => entity.insert(Breach),
+            };
+        }
+        if let Some(name) = self.name {
+            entity.insert(Name::new(name));
+        }
+        if let Some(location) = self.location {
+            entity.insert(location);
+        }
+        if let Some(sprite) = self.sprite {
+            entity.insert(sprite);
+        }
+        if let Some(tint) = self.tint {
+            entity.insert(tint);
+        }
+        if let Some(item_id) = self.item_id {
+            entity.insert(item_id);
+        }
+        if let Some(tags) = self.tags {
+            entity.insert(tags);
+        }
+        if let Some(hands) = self.hands {
+            entity.insert(remapped(hands, mapping));
+        }
+        if let Some(player) = self.player {
+            entity.insert(remapped(player, mapping));
+        }
+        if let Some(interactable) = self.interactable {
+            entity.insert(interactable);
+        }
+        if let Some(machine) = self.vending_machine {
+            entity.insert(remapped(machine, mapping));
+        }
+        if let Some(tool) = self.tool {
+            entity.insert(tool);
+        }
+        if let Some(lighter) = self.lighter {
+            entity.insert(lighter);
+        }
+        if let Some(cigarette) = self.cigarette {
+            entity.insert(cigarette);
+        }
+        if let Some(rng) = self.rng {
+            entity.insert(rng);
+        }
+        if let Some(energy) = self.energy {
+            entity.insert(energy);
+        }
+        if let Some(behavior) = self.behavior {
+            entity.insert(remapped(behavior, mapping));
+        }
+        if let Some(routine) = self.routine {
+            entity.insert(remapped(routine, mapping));
+        }
+        if let Some(flammable) = self.flammable {
+            entity.insert(flammable);
+        }
+        if let Some(burning) = self.burning {
+            entity.insert(burning);
+        }
+        if let Some(smoke) = self.smoke {
+            entity.insert(smoke);
+        }
+        if let Some(health) = self.health {
+            entity.insert(health);
+        }
+        if let Some(panel) = self.panel {
+            entity.insert(panel);
+        }
+        if let Some(anchored) = self.anchored {
+            entity.insert(anchored);
+        }
+        if let Some(wiring) = self.wiring {
+            entity.insert(wiring);
+        }
+        if let Some(source) = self.power_source {
+            entity.insert(source);
+        }
+        if let Some(consumer) = self.power_consumer {
+            entity.insert(consumer);
+        }
+        if let Some(door) = self.door {
+            entity.insert(door);
+        }
+        if let Some(airlock) = self.airlock {
+            entity.insert(remapped(airlock, mapping));
+        }
+        if let Some(card) = self.id_card {
+            entity.insert(card);
+        }
+        if let Some(required) = self.requires_access {
+            entity.insert(required);
+        }
+        if let Some(inventory) = self.inventory {
+            entity.insert(remapped(inventory, mapping));
+        }
+        if let Some(wearable) = self.wearable {
+            entity.insert(wearable);
+        }
+        if let Some(container) = self.container {
+            entity.insert(remapped(container, mapping));
+        }
+        if let Some(storage) = self.storage {
+            entity.insert(storage);
+        }
+    }
+}
+
+pub struct SaveGame(pub String);
+
+impl Command for SaveGame {
+    fn apply(self, world: &mut World) {
+        let entities = saved_entities(world)
+            .into_iter()
+            .map(|entity| SavedEntity::from_world(world, entity))
+            .collect();
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            map_size: world.resource::<Grid<Floor>>().size(),
+            clock: world.resource::<TurnScheduler>().clock,
+            log: world.resource::<Log>().entries.clone(),
+            global_rng: world.resource::<GlobalRng>().clone(),
+            wiring_diagram: world.resource::<WiringDiagram>().clone(),
+            atmosphere: world.resource::<Atmosphere>().clone(),
+            entities,
+        };
+
+        let result = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| fs::write(&self.0, text).map_err(|err| err.to_string()));
+        let message = match result {
+            Ok(()) => format!("Saved to {}", self.0),
+            Err(err) => format!("Save failed: {}", err),
+        };
+        AddToLog(message, None).apply(world);
+    }
+}
+
+pub struct LoadGame(pub String);
+
+impl LoadGame {
+    fn read(&self) -> Result<SaveFile, String> {
+        let text = fs::read_to_string(&self.0).map_err(|err| err.to_string())?;
+        let save: SaveFile = ron::from_str(&text).map_err(|err| err.to_string())?;
+        if save.version != SAVE_VERSION {
+            return Err(format!(
+                "save is version {}, expected {}",
+                save.version, SAVE_VERSION
+            ));
+        }
+        Ok(save)
+    }
+}
+
+impl Command for LoadGame {
+    fn apply(self, world: &mut World) {
+        let save = match self.read() {
+            Ok(save) => save,
+            Err(err) => {
+                AddToLog(format!("Load failed: {}", err), None).apply(world);
+                return;
+            }
+        };
+
+        for entity in saved_entities(world) {
+            despawn_with_children_recursive(world, entity);
+        }
+
+        let mapping = save
+            .entities
+            .iter()
+            .map(|saved| (saved.id, world.spawn_empty().id()))
+            .collect::<HashMap<_, _>>();
+        for saved in save.entities {
+            saved.spawn(world, &mapping);
+        }
+
+        world.send_event(ResizeGrid(save.map_size));
+        world.resource_mut::<TurnScheduler>().clock = save.clock;
+        world.resource_mut::<Log>().entries = save.log;
+        *world.resource_mut::<GlobalRng>() = save.global_rng;
+        world.insert_resource(save.wiring_diagram);
+        world.insert_resource(save.atmosphere);
+        AddToLog(format!("Loaded {}", self.0), None).apply(world);
+    }
+}
+
+pub fn save_load_input(mut commands: Commands, keyboard: Res<Input<KeyCode>>) {
+    if keyboard.just_pressed(KeyCode::F5) {
+        commands.add(SaveGame(SAVE_PATH.to_string()));
+    }
+    if keyboard.just_pressed(KeyCode::F9) {
+        commands.add(LoadGame(SAVE_PATH.to_string()));
+    }
+}
@@ -1,7 +1,25 @@
 use crate::{
-    grid::{GRID_SIZE_X, GRID_SIZE_Y},
-    SCREEN_TILE_SIZE_Y,
+    health::{Health, Vitality},
+    GAME_VIEW_TILE_SIZE_X, GAME_VIEW_TILE_SIZE_Y, SCREEN_TILE_SIZE_Y,
 };
 
-pub const STATUS_SIZE_X: usize = GRID_SIZE_X + 1;
-pub const STATUS_SIZE_Y: usize = SCREEN_TILE_SIZE_Y - GRID_SIZE_Y;
+pub const STATUS_SIZE_X: usize = GAME_VIEW_TILE_SIZE_X + 1;
+pub const STATUS_SIZE_Y: usize = SCREEN_TILE_SIZE_Y - GAME_VIEW_TILE_SIZE_Y;
+
+/// Health, each kind of damage taken and anything ailing the player, for the bottom bar
+pub fn health_status(health: &Health) -> String {
+    let mut status = match health.vitality {
+        Vitality::Alive => format!("Health: {}/{}", health.current(), health.max),
+        Vitality::Unconscious => "Unconscious".to_string(),
+        Vitality::Dead => "Dead".to_string(),
+    };
+    status += &format!(
+        "  Brute: {}  Burn: {}  Toxin: {}  Oxygen: {}",
+        health.brute, health.burn, health.toxin, health.oxygen
+    );
+    for effect in &health.effects {
+        status += "  ";
+        status += effect.kind.name();
+    }
+    status
+}
@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::{GameSprite, Impassable, TintOverride},
+    grid::{GridLocation, LockToGrid},
+    hands::Hands,
+    interactable::Interactable,
+    inventory::Container,
+    items::ItemTags,
+    log::AddToLog,
+    menu::{CentralMenu, CloseMenu, MenuRedraw},
+    player::{Player, PlayerTookTurn},
+    turn::PICKUP_COST,
+};
+
+pub const CRATE_CAPACITY: usize = 10;
+pub const LOCKER_CAPACITY: usize = 8;
+
+/// Lockers, crates and anything else that sits on the grid and is opened to get at its
+/// `Container`
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Storage {
+    pub selection: usize,
+}
+
+pub fn spawn_storage(
+    commands: &mut Commands,
+    location: GridLocation,
+    name: &str,
+    glyph: char,
+    container: Container,
+) -> Entity {
+    commands
+        .spawn((
+            location,
+            LockToGrid,
+            Name::new(name.to_string()),
+            Interactable::Storage,
+            Storage::default(),
+            container,
+            ItemTags(vec!["storage".to_string()]),
+            Impassable,
+            GameSprite::Text(glyph),
+            TintOverride(Color::rgb(0.6, 0.6, 0.7)),
+            SpatialBundle::default(),
+        ))
+        .id()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StorageRow {
+    Stored(Entity),
+    PutIn(Entity),
+    Close,
+}
+
+/// Everything inside, then putting away whatever the active hand holds
+fn storage_rows(container: &Container, held: Option<Entity>) -> Vec<StorageRow> {
+    container
+        .contents
+        .iter()
+        .map(|item| StorageRow::Stored(*item))
+        .chain(held.map(StorageRow::PutIn))
+        .chain([StorageRow::Close])
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn storage_menu(
+    mut commands: Commands,
+    menu: Res<CentralMenu>,
+    mut storages: Query<(&mut Storage, &mut Container, Option<&Name>)>,
+    mut player: Query<(&mut Hands, &mut Player)>,
+    names: Query<&Name>,
+    input: Res<Input<KeyCode>>,
+    mut close_menu: EventWriter<CloseMenu>,
+    mut redraw_menu: EventWriter<MenuRedraw>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+) {
+    let Some(owner) = menu.owner else {
+        return;
+    };
+    let Ok((mut storage, mut container, storage_name)) = storages.get_mut(owner) else {
+        return;
+    };
+    let Ok((mut hands, mut player)) = player.get_single_mut() else {
+        return;
+    };
+    let held = hands.get_active_held();
+    let rows = storage_rows(&container, held);
+    storage.selection = storage.selection.min(rows.len() - 1);
+
+    if input.just_pressed(KeyCode::S) {
+        storage.selection = (storage.selection + 1).min(rows.len() - 1);
+        redraw_menu.send(MenuRedraw);
+    }
+    if input.just_pressed(KeyCode::W) {
+        storage.selection = storage.selection.saturating_sub(1);
+        redraw_menu.send(MenuRedraw);
+    }
+    if !input.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let name = |entity: Entity| {
+        names
+            .get(entity)
+            .map_or("item".to_string(), Name::to_string)
+    };
+    let storage_name = storage_name.map_or("storage".to_string(), |name| name.to_lowercase());
+    match rows[storage.selection] {
+        StorageRow::Close => {
+            storage.selection = 0;
+            close_menu.send(CloseMenu);
+            return;
+        }
+        StorageRow::Stored(_) if held.is_some() => {
+            commands.add(AddToLog("Your hand is full".to_string(), None));
+            return;
+        }
+        StorageRow::Stored(item) => {
+            container.contents.retain(|stored| *stored != item);
+            hands.pickup(item);
+            commands.add(AddToLog(
+                format!("You take the {} out of the {}", name(item), storage_name),
+                None,
+            ));
+        }
+        StorageRow::PutIn(_) if container.is_full() => {
+            commands.add(AddToLog(format!("The {} is full", storage_name), None));
+            return;
+        }
+        StorageRow::PutIn(item) => {
+            container.contents.push(item);
+            hands.clear_active();
+            // Whatever left the hand can't be combined or aimed any more
+            if player.combining == Some(item) {
+                player.combining = None;
+            }
+            if player.targeting == Some(item) {
+                player.targeting = None;
+            }
+            commands.add(AddToLog(
+                format!("You put the {} in the {}", name(item), storage_name),
+                None,
+            ));
+        }
+    }
+    turn_event.send(PlayerTookTurn(PICKUP_COST));
+    redraw_menu.send(MenuRedraw);
+}
+
+pub fn update_storage_menu_graphics(
+    mut commands: Commands,
+    mut menu: ResMut<CentralMenu>,
+    storages: Query<(&Storage, &Container, Option<&Name>)>,
+    player: Query<&Hands, With<Player>>,
+    names: Query<&Name>,
+    mut event: EventReader<MenuRedraw>,
+) {
+    for _ev in event.read() {
+        let Some(owner) = menu.owner else {
+            continue;
+        };
+        let Ok((storage, container, storage_name)) = storages.get(owner) else {
+            continue;
+        };
+        let Ok(hands) = player.get_single() else {
+            continue;
+        };
+        menu.clear_menu(&mut commands);
+        let storage_name = storage_name.map_or("Storage".to_string(), Name::to_string);
+        menu.set_row_text(
+            &mut commands,
+            &format!(
+                "{} ({}/{})",
+                storage_name,
+                container.contents.len(),
+                container.capacity
+            ),
+            0,
+            None,
+        );
+        let name = |entity: Entity| {
+            names
+                .get(entity)
+                .map_or("item".to_string(), Name::to_string)
+        };
+        let rows = storage_rows(container, hands.get_active_held());
+        for (i, row) in rows.into_iter().enumerate() {
+            let text = match row {
+                StorageRow::Stored(item) => name(item),
+                StorageRow::PutIn(item) => format!("Put in {}", name(item)),
+                StorageRow::Close => "Close".to_string(),
+            };
+            let tint = (i == storage.selection).then_some(TintOverride(Color::YELLOW));
+            menu.set_row_text(&mut commands, &text, i + 1, tint);
+        }
+    }
+}
@@ -13,6 +13,7 @@ pub struct AsciiText {
 
 #[derive(Component)]
 pub struct Character {
+    #[allow(unused)]
     pub character_index: usize,
 }
 
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    player::{Player, PlayerTookTurn},
+    wfc::WfcState,
+};
+
+// Tick costs of the things actors can do, a normal speed actor moves once per 100 ticks
+pub const MOVE_COST: u64 = 100;
+pub const INTERACT_COST: u64 = 100;
+pub const PICKUP_COST: u64 = 50;
+pub const USE_COST: u64 = 50;
+pub const COMBINE_COST: u64 = 200;
+pub const NPC_ACTION_COST: u64 = MOVE_COST;
+
+pub const NORMAL_SPEED: u64 = 100;
+
+/// Global in-game clock and the actors whose time has come this step
+#[derive(Resource, Default)]
+pub struct TurnScheduler {
+    pub clock: u64,
+    acting: HashSet<Entity>,
+}
+
+impl TurnScheduler {
+    pub fn is_acting(&self, entity: Entity) -> bool {
+        self.acting.contains(&entity)
+    }
+}
+
+/// Actors with energy act whenever the clock reaches `ready_at`
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Energy {
+    pub ready_at: u64,
+    /// Percentage of normal speed, higher is faster
+    pub speed: u64,
+}
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self {
+            ready_at: 0,
+            speed: NORMAL_SPEED,
+        }
+    }
+}
+
+impl Energy {
+    pub fn cost(&self, ticks: u64) -> u64 {
+        (ticks * NORMAL_SPEED / self.speed.max(1)).max(1)
+    }
+}
+
+pub fn spend_player_energy(
+    mut events: EventReader<PlayerTookTurn>,
+    mut player: Query<&mut Energy, With<Player>>,
+    scheduler: Res<TurnScheduler>,
+) {
+    let Ok(mut energy) = player.get_single_mut() else {
+        return;
+    };
+    for PlayerTookTurn(ticks) in events.read() {
+        energy.ready_at = energy.ready_at.max(scheduler.clock) + energy.cost(*ticks);
+    }
+}
+
+/// Steps the clock to the next actor that is ready, stopping whenever the player needs to act
+pub fn run_scheduler(
+    mut scheduler: ResMut<TurnScheduler>,
+    mut actors: Query<(Entity, &mut Energy, Has<Player>)>,
+) {
+    scheduler.acting.clear();
+    let Some(player_ready) = actors
+        .iter()
+        .find(|(_, _, is_player)| *is_player)
+        .map(|(_, energy, _)| energy.ready_at)
+    else {
+        return;
+    };
+    if player_ready <= scheduler.clock {
+        return;
+    }
+
+    let next = actors
+        .iter()
+        .filter(|(_, _, is_player)| !is_player)
+        .map(|(_, energy, _)| energy.ready_at)
+        .min()
+        .map_or(player_ready, |ready| ready.min(player_ready))
+        .max(scheduler.clock);
+    scheduler.clock = next;
+
+    for (entity, mut energy, is_player) in &mut actors {
+        if !is_player && energy.ready_at <= next {
+            energy.ready_at = next + energy.cost(NPC_ACTION_COST);
+            scheduler.acting.insert(entity);
+        }
+    }
+}
+
+/// The clock has reached the player, whether or not a level is generating
+pub fn player_turn() -> impl Condition<()> {
+    IntoSystem::into_system(
+        |scheduler: Res<TurnScheduler>, player: Query<&Energy, With<Player>>| {
+            player
+                .get_single()
+                .map(|energy| energy.ready_at <= scheduler.clock)
+                .unwrap_or(false)
+        },
+    )
+}
+
+/// The player's turn has come, held back while a new level generates so nothing happens on the
+/// old one in the meantime
+pub fn player_ready() -> impl Condition<()> {
+    player_turn().and_then(IntoSystem::into_system(|wfc: Res<WfcState>| !wfc.running()))
+}
+
+pub fn npcs_acting() -> impl Condition<()> {
+    IntoSystem::into_system(|scheduler: Res<TurnScheduler>| !scheduler.acting.is_empty())
+}
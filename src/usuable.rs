@@ -1,11 +1,12 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{graphics::TintOverride, log::AddToLog, player::PlayerCombined, Cigarette};
+use crate::{graphics::TintOverride, log::AddToLog};
 
 #[derive(Event)]
 pub struct PlayerUsed(pub Entity);
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Lighter {
     pub active: bool,
 }
@@ -30,24 +31,3 @@ pub fn use_lighter(
         }
     }
 }
-
-pub fn use_lighter_on_cig(
-    mut commands: Commands,
-    mut events: EventReader<PlayerCombined>,
-    lighters: Query<&Lighter>,
-    mut cigs: Query<&mut Cigarette>,
-) {
-    for event in events.read() {
-        if let Ok(lighter) = lighters.get(event.0) {
-            if let Ok(mut cig) = cigs.get_mut(event.1) {
-                if lighter.active {
-                    commands.add(AddToLog("Lit Cigarette".to_string(), None));
-                    cig.burning = true;
-                    commands.entity(event.1).insert(TintOverride(Color::ORANGE));
-                } else {
-                    commands.add(AddToLog("The lighter is off!".to_string(), None));
-                }
-            }
-        }
-    }
-}
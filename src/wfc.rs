@@ -1,14 +1,24 @@
 use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
 use bevy_inspector_egui::prelude::*;
+use bevy_turborand::{DelegatedRng, GlobalRng};
+use futures_lite::future;
 use image::*;
+use rand::{rngs::StdRng, SeedableRng};
 use std::fs::File;
-use std::io::Write;
 use std::io::{self, BufRead};
 use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use wfc_image::*;
 
-use crate::grid::{GRID_SIZE_X, GRID_SIZE_Y};
+use crate::grid::Grid;
 use crate::log::AddToLog;
+use crate::map::LoadMap;
+use crate::Floor;
+
+// Attempts after the first contradiction before giving up
+const WFC_RETRIES: usize = 10;
 
 #[derive(Reflect, Resource, InspectorOptions)]
 #[reflect(Resource, InspectorOptions)]
@@ -17,6 +27,33 @@ pub struct WfcSettings {
     pattern_size: u32,
 }
 
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WfcState {
+    #[default]
+    Idle,
+    /// Counts from 1, every contradiction starts another attempt
+    Running {
+        attempt: usize,
+    },
+    Failed,
+    Done,
+}
+
+impl WfcState {
+    pub fn running(&self) -> bool {
+        matches!(self, WfcState::Running { .. })
+    }
+}
+
+#[derive(Component)]
+pub struct WfcTask {
+    task: Task<Result<Vec<Vec<char>>, String>>,
+    /// Checked between attempts, dropping the task alone won't stop one that's already running
+    cancelled: Arc<AtomicBool>,
+    /// The attempt the task is on, shared so progress can be reported while it runs
+    attempt: Arc<AtomicUsize>,
+}
+
 impl Default for WfcSettings {
     fn default() -> Self {
         Self { pattern_size: 3 }
@@ -43,49 +80,120 @@ pub fn text_to_image(path: &str) -> io::Result<DynamicImage> {
     Ok(DynamicImage::ImageLuma8(image))
 }
 
-pub fn image_to_text(image: DynamicImage, output_path: &str) -> io::Result<()> {
+pub fn image_to_rows(image: DynamicImage) -> Vec<Vec<char>> {
     let gray_image = image.into_luma8();
-    let mut file = File::create(output_path)?;
 
-    for y in 0..gray_image.height() {
-        for x in 0..gray_image.width() {
-            let pixel = gray_image.get_pixel(x, y);
-            let value = pixel[0] as char;
-            write!(file, "{}", value)?;
+    (0..gray_image.height())
+        .map(|y| {
+            (0..gray_image.width())
+                .map(|x| gray_image.get_pixel(x, y)[0] as char)
+                .collect()
+        })
+        .collect()
+}
+
+pub fn wfc(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    settings: Res<WfcSettings>,
+    grid: Res<Grid<Floor>>,
+    mut state: ResMut<WfcState>,
+    mut global_rng: ResMut<GlobalRng>,
+    current_tasks: Query<(Entity, &WfcTask)>,
+) {
+    if input.just_pressed(KeyCode::Space) {
+        for (entity, task) in &current_tasks {
+            task.cancelled.store(true, Ordering::Relaxed);
+            commands.entity(entity).despawn_recursive();
+            commands.add(AddToLog("Cancelled previous generation".to_string(), None));
         }
-        writeln!(file)?;
-    }
 
-    Ok(())
+        let Some(pattern_size) = NonZeroU32::new(settings.pattern_size) else {
+            commands.add(AddToLog("Pattern size may not be zero".to_string(), None));
+            *state = WfcState::Failed;
+            return;
+        };
+
+        let size = grid.size();
+        // Seeded from the run so a replay generates the same level
+        let seed = global_rng.u64(..);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task_cancelled = cancelled.clone();
+        let attempt = Arc::new(AtomicUsize::new(1));
+        let task_attempt = attempt.clone();
+        let thread_pool = AsyncComputeTaskPool::get();
+        let task = thread_pool.spawn(async move {
+            let input_image = text_to_image("assets/input.txt")
+                .map_err(|err| format!("Could not read wfc input: {}", err))?;
+            let output_size = Size::new(size.x, size.y);
+            let mut rng = StdRng::seed_from_u64(seed);
+
+            for attempt in 1..=WFC_RETRIES + 1 {
+                task_attempt.store(attempt, Ordering::Relaxed);
+                if task_cancelled.load(Ordering::Relaxed) {
+                    return Err("Cancelled".to_string());
+                }
+                if let Ok(image) = generate_image_with_rng(
+                    &input_image,
+                    pattern_size,
+                    output_size,
+                    &orientation::ALL,
+                    WrapXY,
+                    ForbidNothing,
+                    retry::NumTimes(0),
+                    &mut rng,
+                ) {
+                    return Ok(image_to_rows(image));
+                }
+            }
+            Err("Too many contradictions".to_string())
+        });
+
+        commands.spawn(WfcTask {
+            task,
+            cancelled,
+            attempt,
+        });
+        commands.add(AddToLog("Generating...".to_string(), None));
+        *state = WfcState::Running { attempt: 1 };
+    }
 }
 
-pub fn wfc(mut commands: Commands, input: Res<Input<KeyCode>>, settings: Res<WfcSettings>) {
-    if input.just_pressed(KeyCode::Space) {
-        commands.add(AddToLog("Generated".to_string(), None));
-        let orientation = &orientation::ALL;
-        let input_image = text_to_image("assets/input.txt").unwrap();
-
-        let output_size = Size::new(GRID_SIZE_X as u32, GRID_SIZE_Y as u32);
-        let start_time = ::std::time::Instant::now();
-        let pattern_size =
-            NonZeroU32::new(settings.pattern_size).expect("pattern size may not be zero");
-        let result = generate_image(
-            &input_image,
-            pattern_size,
-            output_size,
-            orientation,
-            WrapXY,
-            ForbidNothing,
-            retry::NumTimes(10),
-        );
-        match result {
-            Err(_) => {
-                eprintln!("Too many contradictions");
+pub fn resolve_wfc(
+    mut commands: Commands,
+    mut state: ResMut<WfcState>,
+    mut tasks: Query<(Entity, &mut WfcTask)>,
+    mut load_map: EventWriter<LoadMap>,
+) {
+    for (task_entity, mut task) in &mut tasks {
+        // Replaced by a newer request this frame, its despawn hasn't been applied yet
+        if task.cancelled.load(Ordering::Relaxed) {
+            continue;
+        }
+        let result = future::block_on(future::poll_once(&mut task.task));
+        // Read after polling so a finished task has reported every attempt it made
+        let attempt = task.attempt.load(Ordering::Relaxed);
+        if let WfcState::Running { attempt: reported } = *state {
+            for retry in reported + 1..=attempt {
+                commands.add(AddToLog(
+                    format!("Contradiction, retrying ({}/{})", retry, WFC_RETRIES + 1),
+                    None,
+                ));
             }
-            Ok(output_image) => {
-                let end_time = ::std::time::Instant::now();
-                println!("{:?}", end_time - start_time);
-                image_to_text(output_image, "result.txt").unwrap();
+            *state = WfcState::Running { attempt };
+        }
+        if let Some(result) = result {
+            commands.entity(task_entity).despawn_recursive();
+            match result {
+                Ok(rows) => {
+                    commands.add(AddToLog("Generated".to_string(), None));
+                    load_map.send(LoadMap(rows));
+                    *state = WfcState::Done;
+                }
+                Err(reason) => {
+                    commands.add(AddToLog(reason, None));
+                    *state = WfcState::Failed;
+                }
             }
         }
     }
@@ -0,0 +1,368 @@
+use bevy::{ecs::system::Command, prelude::*};
+use bevy_turborand::DelegatedRng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::TintOverride,
+    grid::GridLocation,
+    hands::Hands,
+    health::{Damage, DamageKind},
+    interactable::{Interactable, VendingMachine},
+    log::AddToLog,
+    menu::{CentralMenu, CloseMenu, MenuRedraw, OpenMenu},
+    player::{Player, PlayerTookTurn},
+    power::PowerConsumer,
+    turn::{TurnScheduler, INTERACT_COST, MOVE_COST},
+    usuable::PlayerUsed,
+    Tool,
+};
+
+const WIRE_COLORS: [&str; 8] = [
+    "red", "blue", "green", "yellow", "orange", "purple", "white", "black",
+];
+// How long a pulsed wire stays disrupted
+const PULSE_TURNS: u64 = 10;
+pub const SHOCK_DAMAGE: u32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFunction {
+    Power,
+    Dispense,
+    Shock,
+    Lock,
+    /// Does nothing, there to make guessing harder
+    Dud,
+}
+
+impl WireFunction {
+    fn name(&self) -> &'static str {
+        match self {
+            WireFunction::Power => "power",
+            WireFunction::Dispense => "dispense",
+            WireFunction::Shock => "shock",
+            WireFunction::Lock => "lock",
+            WireFunction::Dud => "nothing",
+        }
+    }
+}
+
+fn machine_functions(kind: &Interactable) -> Vec<WireFunction> {
+    match kind {
+        Interactable::VendingMachine => vec![
+            WireFunction::Power,
+            WireFunction::Dispense,
+            WireFunction::Shock,
+            WireFunction::Lock,
+            WireFunction::Dud,
+            WireFunction::Dud,
+        ],
+        Interactable::Storage => Vec::new(),
+    }
+}
+
+/// Which color wire does what in each kind of machine, the same for every machine on the ship
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+pub struct WiringDiagram {
+    pub layouts: Vec<(Interactable, Vec<(String, WireFunction)>)>,
+}
+
+impl WiringDiagram {
+    pub fn generate(rng: &mut impl DelegatedRng) -> Self {
+        let layouts = [Interactable::VendingMachine]
+            .into_iter()
+            .map(|kind| {
+                let mut colors = WIRE_COLORS.to_vec();
+                let mut functions = machine_functions(&kind);
+                rng.shuffle(&mut colors);
+                rng.shuffle(&mut functions);
+                let wires = colors
+                    .into_iter()
+                    .zip(functions)
+                    .map(|(color, function)| (color.to_string(), function))
+                    .collect();
+                (kind, wires)
+            })
+            .collect();
+        Self { layouts }
+    }
+
+    pub fn wiring_for(&self, kind: &Interactable) -> Wiring {
+        let wires = self
+            .layouts
+            .iter()
+            .find(|(layout_kind, _)| layout_kind == kind)
+            .map(|(_, wires)| wires.as_slice())
+            .unwrap_or_default();
+        Wiring {
+            wires: wires
+                .iter()
+                .map(|(color, function)| Wire {
+                    color: color.clone(),
+                    function: *function,
+                    cut: false,
+                    pulsed_until: 0,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Wire {
+    pub color: String,
+    pub function: WireFunction,
+    pub cut: bool,
+    pub pulsed_until: u64,
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct Wiring {
+    pub wires: Vec<Wire>,
+}
+
+impl Wiring {
+    /// Cut or recently pulsed
+    pub fn disrupted(&self, function: WireFunction, clock: u64) -> bool {
+        self.wires
+            .iter()
+            .any(|wire| wire.function == function && (wire.cut || wire.pulsed_until > clock))
+    }
+
+    pub fn powered(&self, clock: u64) -> bool {
+        !self.disrupted(WireFunction::Power, clock)
+    }
+
+    pub fn electrified(&self, clock: u64) -> bool {
+        self.powered(clock) && self.disrupted(WireFunction::Shock, clock)
+    }
+
+    pub fn locked(&self, clock: u64) -> bool {
+        self.disrupted(WireFunction::Lock, clock)
+    }
+
+    pub fn can_dispense(&self, clock: u64) -> bool {
+        self.powered(clock) && !self.disrupted(WireFunction::Dispense, clock)
+    }
+}
+
+/// Printed copy of the wiring diagram, reading it tells you what every wire does
+#[derive(Component, Clone, Default)]
+pub struct DiagramSheet;
+
+pub fn read_diagram(
+    mut commands: Commands,
+    mut events: EventReader<PlayerUsed>,
+    sheets: Query<(), With<DiagramSheet>>,
+    diagram: Res<WiringDiagram>,
+) {
+    for PlayerUsed(entity) in events.read() {
+        if !sheets.contains(*entity) {
+            continue;
+        }
+        for (kind, wires) in &diagram.layouts {
+            let wires = wires
+                .iter()
+                .map(|(color, function)| format!("{} {}", color, function.name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            commands.add(AddToLog(format!("{:?}: {}", kind, wires), None));
+        }
+    }
+}
+
+/// Menu over the insides of a machine with an open panel
+#[derive(Component)]
+pub struct WiringMenu {
+    pub machine: Entity,
+    selection: usize,
+}
+
+impl WiringMenu {
+    pub fn new(machine: Entity) -> Self {
+        Self {
+            machine,
+            selection: 0,
+        }
+    }
+}
+
+pub struct OpenWiring(pub Entity);
+
+impl Command for OpenWiring {
+    fn apply(self, world: &mut World) {
+        if world.resource::<CentralMenu>().open {
+            return;
+        }
+        let menu = world.spawn(WiringMenu::new(self.0)).id();
+        world.send_event(OpenMenu(menu));
+    }
+}
+
+/// Return cuts or mends the selected wire with wirecutters, use pulses it with a multitool
+#[allow(clippy::too_many_arguments)]
+pub fn wiring_menu(
+    mut commands: Commands,
+    menu: Res<CentralMenu>,
+    mut wiring_menus: Query<&mut WiringMenu>,
+    mut machines: Query<(
+        &mut Wiring,
+        Option<&Name>,
+        Option<&mut VendingMachine>,
+        Option<&PowerConsumer>,
+    )>,
+    player: Query<(Entity, &Hands, &GridLocation), With<Player>>,
+    tools: Query<&Tool>,
+    scheduler: Res<TurnScheduler>,
+    input: Res<Input<KeyCode>>,
+    mut close_menu: EventWriter<CloseMenu>,
+    mut redraw_menu: EventWriter<MenuRedraw>,
+    mut turn_event: EventWriter<PlayerTookTurn>,
+    mut damage: EventWriter<Damage>,
+) {
+    let Some(owner) = menu.owner else {
+        return;
+    };
+    let Ok(mut wiring_menu) = wiring_menus.get_mut(owner) else {
+        return;
+    };
+    let Ok((mut wiring, name, vending, power)) = machines.get_mut(wiring_menu.machine) else {
+        return;
+    };
+    let Ok((player, hands, location)) = player.get_single() else {
+        return;
+    };
+
+    if input.just_pressed(KeyCode::S) {
+        wiring_menu.selection = (wiring_menu.selection + 1).min(wiring.wires.len());
+        redraw_menu.send(MenuRedraw);
+    }
+    if input.just_pressed(KeyCode::W) {
+        wiring_menu.selection = wiring_menu.selection.saturating_sub(1);
+        redraw_menu.send(MenuRedraw);
+    }
+
+    let cutting = input.just_pressed(KeyCode::Return);
+    let pulsing = input.just_pressed(KeyCode::Z);
+    if !cutting && !pulsing {
+        return;
+    }
+    // The row past the last wire closes the panel view
+    if wiring_menu.selection >= wiring.wires.len() {
+        if cutting {
+            close_menu.send(CloseMenu);
+            commands.entity(owner).despawn_recursive();
+        }
+        return;
+    }
+
+    let held = hands
+        .get_active_held()
+        .and_then(|held| tools.get(held).ok());
+    let (needed, tool_name) = if cutting {
+        (Tool::Wirecutters, "wirecutters")
+    } else {
+        (Tool::Multitool, "a multitool")
+    };
+    if held != Some(&needed) {
+        commands.add(AddToLog(format!("You need {} for that", tool_name), None));
+        return;
+    }
+
+    turn_event.send(PlayerTookTurn(INTERACT_COST));
+    let powered = power.is_none_or(|power| power.powered);
+    if powered && wiring.electrified(scheduler.clock) {
+        damage.send(Damage {
+            target: player,
+            kind: DamageKind::Shock,
+            amount: SHOCK_DAMAGE,
+        });
+    }
+
+    let clock = scheduler.clock;
+    // Checked before the pulse lands, which disrupts the wire it's on
+    let can_dispense = powered && wiring.can_dispense(clock);
+    let wire = &mut wiring.wires[wiring_menu.selection];
+    if cutting {
+        wire.cut = !wire.cut;
+        let verb = if wire.cut { "cut" } else { "mend" };
+        commands.add(AddToLog(
+            format!("You {} the {} wire", verb, wire.color),
+            None,
+        ));
+    } else if wire.cut {
+        commands.add(AddToLog(format!("The {} wire is cut", wire.color), None));
+    } else {
+        wire.pulsed_until = clock + PULSE_TURNS * MOVE_COST;
+        commands.add(AddToLog(format!("You pulse the {} wire", wire.color), None));
+
+        let function = wire.function;
+        let name = name.map_or("machine".to_string(), |name| name.to_lowercase());
+        match function {
+            WireFunction::Power => {
+                commands.add(AddToLog(format!("The {} flickers", name), None));
+            }
+            // A pulse kicks a working dispenser into spitting something out
+            WireFunction::Dispense if can_dispense => {
+                if let Some(mut vending) = vending {
+                    if !vending.options.is_empty() {
+                        let item = vending.options.remove(0);
+                        vending.selection = 0;
+                        commands
+                            .entity(item)
+                            .insert((location.clone(), Visibility::Visible));
+                        commands.add(AddToLog(format!("The {} spits something out", name), None));
+                    }
+                }
+            }
+            WireFunction::Dispense => {}
+            WireFunction::Shock => {
+                commands.add(AddToLog(format!("The {} crackles", name), None));
+            }
+            WireFunction::Lock => {
+                commands.add(AddToLog(format!("The {} clicks", name), None));
+            }
+            WireFunction::Dud => {}
+        }
+    }
+    redraw_menu.send(MenuRedraw);
+}
+
+pub fn update_wiring_menu_graphics(
+    mut commands: Commands,
+    mut menu: ResMut<CentralMenu>,
+    wiring_menus: Query<&WiringMenu>,
+    machines: Query<(&Wiring, Option<&Name>)>,
+    mut event: EventReader<MenuRedraw>,
+) {
+    for _ev in event.read() {
+        let Some(owner) = menu.owner else {
+            continue;
+        };
+        let Ok(wiring_menu) = wiring_menus.get(owner) else {
+            continue;
+        };
+        let Ok((wiring, name)) = machines.get(wiring_menu.machine) else {
+            continue;
+        };
+        menu.clear_menu(&mut commands);
+        let name = name.map_or("machine".to_string(), |name| name.to_lowercase());
+        menu.set_row_text(&mut commands, &format!("Wiring of the {}", name), 0, None);
+        let selected = Some(TintOverride(Color::YELLOW));
+        for (i, wire) in wiring.wires.iter().enumerate() {
+            let state = if wire.cut { " (cut)" } else { "" };
+            let text = format!("{} {}{}", i + 1, wire.color, state);
+            let tint = if i == wiring_menu.selection {
+                selected.clone()
+            } else {
+                None
+            };
+            menu.set_row_text(&mut commands, &text, i + 1, tint);
+        }
+        let tint = if wiring_menu.selection >= wiring.wires.len() {
+            selected
+        } else {
+            None
+        };
+        menu.set_row_text(&mut commands, "Close", wiring.wires.len() + 1, tint);
+    }
+}
@@ -0,0 +1,49 @@
+use logic_ss_13::{
+    door::Door, grid::GridLocation, headless::HeadlessGame, replay::PlayerAction, DIVIDER_X,
+};
+
+#[test]
+fn bumping_a_door_opens_it() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X - 1, 2));
+    game.act(PlayerAction::Right);
+    assert_eq!(game.player_location(), GridLocation::new(DIVIDER_X - 1, 2));
+    game.act_all([PlayerAction::Right; 2]);
+    assert_eq!(game.player_location(), GridLocation::new(DIVIDER_X + 1, 2));
+}
+
+#[test]
+fn restricted_door_turns_away_anyone_without_access() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X + 1, 6));
+    game.act(PlayerAction::Left);
+    assert_eq!(game.log().last().map(String::as_str), Some("Access denied"));
+    game.act(PlayerAction::Left);
+    assert_eq!(game.player_location(), GridLocation::new(DIVIDER_X + 1, 6));
+}
+
+#[test]
+fn held_id_card_opens_restricted_door() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X + 1, 6));
+    game.give("engineering_id");
+    game.act_all([PlayerAction::Left; 3]);
+    assert_eq!(game.player_location(), GridLocation::new(DIVIDER_X - 1, 6));
+}
+
+#[test]
+fn door_stays_open_for_someone_stepping_in_as_it_closes() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X - 1, 2));
+    game.act(PlayerAction::Right);
+    let door = game.named("Door");
+    assert_eq!(
+        game.world().get::<GridLocation>(door),
+        Some(&GridLocation::new(DIVIDER_X, 2))
+    );
+    // Due to shut the same frame the player arrives, before the grid has caught up with them
+    let clock = game.clock();
+    game.world_mut().get_mut::<Door>(door).unwrap().closes_at = clock;
+    game.teleport(GridLocation::new(DIVIDER_X, 2));
+    assert!(game.world().get::<Door>(door).unwrap().open);
+}
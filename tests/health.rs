@@ -0,0 +1,25 @@
+use logic_ss_13::{
+    headless::HeadlessGame,
+    health::{Damage, DamageKind},
+    replay::PlayerAction,
+    HUMAN_HEALTH,
+};
+
+#[test]
+fn knocked_out_player_comes_to_in_good_air() {
+    let mut game = HeadlessGame::new(0);
+    let player = game.player();
+    game.world_mut().send_event(Damage {
+        target: player,
+        kind: DamageKind::Oxygen,
+        // Good air undoes a little each turn, enough over to stay out for a few
+        amount: HUMAN_HEALTH + 2,
+    });
+    game.wait_turns(1);
+    assert!(game.logged("You pass out"));
+    let location = game.player_location();
+    game.act(PlayerAction::Up);
+    assert_eq!(game.player_location(), location);
+    game.wait_turns(3);
+    assert!(game.logged("You come to"));
+}
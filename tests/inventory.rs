@@ -0,0 +1,59 @@
+use logic_ss_13::{
+    grid::GridLocation, headless::HeadlessGame, inventory::EquipSlot, replay::PlayerAction,
+    DIVIDER_X,
+};
+
+/// Opens the inventory and clips the held card into the id slot
+fn wear_id(game: &mut HeadlessGame) {
+    game.act(PlayerAction::Inventory);
+    assert!(game.menu_open());
+    game.act_all([PlayerAction::Down; 4]);
+    game.act(PlayerAction::Confirm);
+    // Close sits right under the last slot
+    game.act_all([PlayerAction::Down; 3]);
+    game.act(PlayerAction::Confirm);
+    assert!(!game.menu_open());
+}
+
+#[test]
+fn id_card_goes_in_the_id_slot() {
+    let mut game = HeadlessGame::new(0);
+    let card = game.give("engineering_id");
+    wear_id(&mut game);
+    assert!(game.held_names().is_empty());
+    assert_eq!(game.inventory().get(EquipSlot::Id), Some(card));
+}
+
+#[test]
+fn items_only_go_in_slots_they_fit() {
+    let mut game = HeadlessGame::new(0);
+    game.give("screwdriver");
+    game.act(PlayerAction::Inventory);
+    game.act(PlayerAction::Confirm);
+    assert!(game.logged("The Screwdriver doesn't go there"));
+    assert_eq!(game.inventory().get(EquipSlot::Head), None);
+}
+
+#[test]
+fn occupied_slots_are_not_swapped() {
+    let mut game = HeadlessGame::new(0);
+    let hat = game.give("hard_hat");
+    game.act(PlayerAction::Inventory);
+    game.act(PlayerAction::Confirm);
+    assert_eq!(game.inventory().get(EquipSlot::Head), Some(hat));
+    let spare = game.give("hard_hat");
+    game.act(PlayerAction::Confirm);
+    assert!(game.logged("Your head slot is already taken"));
+    assert_eq!(game.inventory().get(EquipSlot::Head), Some(hat));
+    assert_eq!(game.hands().get_active_held(), Some(spare));
+}
+
+#[test]
+fn worn_id_card_opens_restricted_door() {
+    let mut game = HeadlessGame::new(0);
+    game.give("engineering_id");
+    wear_id(&mut game);
+    game.teleport(GridLocation::new(DIVIDER_X + 1, 6));
+    game.act_all([PlayerAction::Left; 3]);
+    assert_eq!(game.player_location(), GridLocation::new(DIVIDER_X - 1, 6));
+}
@@ -0,0 +1,78 @@
+use bevy::{ecs::system::Command, prelude::*};
+use logic_ss_13::{
+    fire::BurnOut,
+    grid::GridLocation,
+    hands::Hands,
+    headless::HeadlessGame,
+    inventory::Container,
+    items::{ItemDefinitions, ItemLibrary, ItemTags, ReplaceItem},
+    replay::PlayerAction,
+    usuable::Lighter,
+};
+
+#[test]
+fn reloading_definitions_keeps_a_lit_lighter_lit() {
+    let mut game = HeadlessGame::new(0);
+    let lighter = game.give("lighter");
+    game.act(PlayerAction::Use);
+    // Stand in for an edit to the file
+    let world = game.world_mut();
+    *world.get_mut::<ItemTags>(lighter).unwrap() = ItemTags::default();
+    *world.get_mut::<Name>(lighter).unwrap() = Name::new("Edited");
+    let id = world.resource::<ItemLibrary>().definitions.id();
+    world.send_event(AssetEvent::<ItemDefinitions>::Modified { id });
+    game.app.update();
+
+    let world = game.world();
+    assert_eq!(world.get::<Name>(lighter).unwrap().as_str(), "Lighter");
+    assert!(world.get::<ItemTags>(lighter).unwrap().has("fire_source"));
+    assert!(world.get::<Lighter>(lighter).unwrap().active);
+    assert!(game.logged("Reloaded item definitions"));
+}
+
+/// A held box with a sheet of paper inside
+fn box_of_paper(game: &mut HeadlessGame) -> (Entity, Entity) {
+    let paper = game.give("paper");
+    let player = game.player();
+    game.world_mut()
+        .get_mut::<Hands>(player)
+        .unwrap()
+        .release(paper);
+    let holder = game.give("box");
+    game.world_mut()
+        .get_mut::<Container>(holder)
+        .unwrap()
+        .contents
+        .push(paper);
+    (holder, paper)
+}
+
+#[test]
+fn replacing_a_container_drops_what_was_in_it() {
+    let mut game = HeadlessGame::new(0);
+    let (holder, paper) = box_of_paper(&mut game);
+    ReplaceItem {
+        entity: holder,
+        id: "paper".to_string(),
+    }
+    .apply(game.world_mut());
+    let world = game.world();
+    assert!(world.get::<Container>(holder).is_none());
+    assert_eq!(
+        world.get::<GridLocation>(paper),
+        Some(&game.player_location())
+    );
+}
+
+#[test]
+fn burnt_out_container_drops_what_was_in_it() {
+    let mut game = HeadlessGame::new(0);
+    let (holder, paper) = box_of_paper(&mut game);
+    BurnOut(holder).apply(game.world_mut());
+    let world = game.world();
+    assert!(world.get_entity(holder).is_none());
+    assert_eq!(
+        world.get::<GridLocation>(paper),
+        Some(&game.player_location())
+    );
+}
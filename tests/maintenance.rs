@@ -0,0 +1,82 @@
+use logic_ss_13::{
+    grid::GridLocation,
+    headless::HeadlessGame,
+    replay::PlayerAction,
+    wiring::{WireFunction, Wiring},
+};
+
+/// Next to the vending machine with a screwdriver in hand
+fn at_vending_machine() -> HeadlessGame {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(1, 4));
+    game.give("screwdriver");
+    game
+}
+
+#[test]
+fn screwdriver_opens_and_closes_the_panel() {
+    let mut game = at_vending_machine();
+    game.act_all([PlayerAction::Use, PlayerAction::Right]);
+    assert!(game.logged("You open the maintenance panel of the vending machine"));
+    game.act_all([PlayerAction::Use, PlayerAction::Right]);
+    assert!(game.logged("You close the maintenance panel of the vending machine"));
+}
+
+#[test]
+fn open_panel_shows_the_wiring() {
+    let mut game = at_vending_machine();
+    game.act_all([PlayerAction::Use, PlayerAction::Right]);
+    game.act(PlayerAction::Right);
+    assert!(game.menu_open());
+    game.act(PlayerAction::Confirm);
+    assert!(game.logged("You need wirecutters for that"));
+    // The close row sits under the last wire, moving down stops there
+    game.act_all([PlayerAction::Down; 8]);
+    game.act(PlayerAction::Confirm);
+    assert!(!game.menu_open());
+}
+
+/// Opens the panel and the wiring with a multitool in the other hand, then pulses the wire
+fn pulse(game: &mut HeadlessGame, function: WireFunction) {
+    game.act_all([PlayerAction::Use, PlayerAction::Right]);
+    game.act(PlayerAction::SwapHand);
+    game.give("multitool");
+    game.act(PlayerAction::Right);
+    assert!(game.menu_open());
+    let machine = game.named("Vending machine");
+    let wire = game
+        .world()
+        .get::<Wiring>(machine)
+        .unwrap()
+        .wires
+        .iter()
+        .position(|wire| wire.function == function)
+        .unwrap();
+    game.act_all(vec![PlayerAction::Down; wire]);
+    game.act(PlayerAction::Use);
+}
+
+#[test]
+fn pulsing_dispense_vends() {
+    let mut game = at_vending_machine();
+    pulse(&mut game, WireFunction::Dispense);
+    assert!(game.logged("The vending machine spits something out"));
+}
+
+#[test]
+fn pulsing_dispense_without_power_does_nothing() {
+    let mut game = at_vending_machine();
+    let machine = game.named("Vending machine");
+    let mut wiring = game.world_mut().get_mut::<Wiring>(machine).unwrap();
+    for wire in wiring.wires.iter_mut() {
+        if wire.function == WireFunction::Power {
+            wire.cut = true;
+        }
+    }
+    pulse(&mut game, WireFunction::Dispense);
+    assert!(game
+        .log()
+        .iter()
+        .any(|entry| entry.starts_with("You pulse the")));
+    assert!(!game.logged("The vending machine spits something out"));
+}
@@ -0,0 +1,52 @@
+use logic_ss_13::{
+    headless::HeadlessGame,
+    map::LoadMap,
+    replay::PlayerAction,
+    wfc::{WfcState, WfcTask},
+};
+
+const ROOM: [&str; 4] = ["##########", "#........#", "#........#", "##########"];
+
+#[test]
+fn loading_a_map_clears_the_old_level() {
+    let mut game = HeadlessGame::new(0);
+    let rows = ROOM.iter().map(|row| row.chars().collect()).collect();
+    game.world_mut().send_event(LoadMap(rows));
+    game.app.update();
+    game.settle();
+    for fixture in [
+        "Vending machine",
+        "Generator",
+        "Crate",
+        "Engineering locker",
+        "Engineering ID",
+        "Paper",
+        "Toolbelt",
+    ] {
+        assert!(
+            game.find_named(fixture).is_none(),
+            "{} was left behind",
+            fixture
+        );
+    }
+}
+
+#[test]
+fn generating_again_replaces_the_running_generation() {
+    let mut game = HeadlessGame::new(0);
+    game.press(PlayerAction::Generate.key());
+    assert!(game.generating());
+    // Pressed again before the first one is done, the player's turn isn't needed for this
+    game.press(PlayerAction::Generate.key());
+    assert!(game.logged("Cancelled previous generation"));
+    let world = game.world_mut();
+    assert_eq!(world.query::<&WfcTask>().iter(world).count(), 1);
+    game.settle();
+    let generated = game
+        .log()
+        .iter()
+        .filter(|entry| *entry == "Generated")
+        .count();
+    assert_eq!(generated, 1);
+    assert_eq!(*game.world().resource::<WfcState>(), WfcState::Done);
+}
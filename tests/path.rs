@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use logic_ss_13::{
+    ai::{Behavior, Routine},
+    grid::GridLocation,
+    headless::HeadlessGame,
+    path::PathFollower,
+    replay::PlayerAction,
+    turn::Energy,
+    Npc,
+};
+
+#[test]
+fn follower_does_not_step_where_the_player_just_moved() {
+    let mut game = HeadlessGame::new(0);
+    let world = game.world_mut();
+    let npc = world
+        .query_filtered::<Entity, With<Npc>>()
+        .iter(world)
+        .next()
+        .unwrap();
+    // Heading for the tile the player is about to take, and first to act once they have
+    let clock = game.clock();
+    game.world_mut()
+        .entity_mut(npc)
+        .remove::<(Routine, Behavior)>()
+        .insert((
+            GridLocation::new(1, 3),
+            PathFollower::new(GridLocation::new(1, 2)),
+            Energy {
+                ready_at: clock,
+                ..Default::default()
+            },
+        ));
+    game.app.update();
+
+    game.act(PlayerAction::Up);
+    assert_eq!(game.player_location(), GridLocation::new(1, 2));
+    assert_ne!(
+        game.world().get::<GridLocation>(npc),
+        Some(&GridLocation::new(1, 2))
+    );
+}
@@ -0,0 +1,11 @@
+use logic_ss_13::{headless::HeadlessGame, power::PowerConsumer};
+
+#[test]
+fn generator_powers_everything_cabled_to_it() {
+    let mut game = HeadlessGame::new(0);
+    game.wait_turns(1);
+    let world = game.world_mut();
+    let mut consumers = world.query::<&PowerConsumer>();
+    assert!(consumers.iter(world).count() > 0);
+    assert!(consumers.iter(world).all(|consumer| consumer.powered));
+}
@@ -0,0 +1,16 @@
+use logic_ss_13::{headless::HeadlessGame, replay::PlayerAction};
+
+#[test]
+fn lit_lighter_lights_a_cigarette() {
+    let mut game = HeadlessGame::new(0);
+    game.give("cigarette");
+    game.act(PlayerAction::SwapHand);
+    game.give("lighter");
+    game.act(PlayerAction::Use);
+    // The cigarette goes first, recipes don't care which hand holds the tool
+    game.act(PlayerAction::SwapHand);
+    game.act(PlayerAction::Combine);
+    game.act(PlayerAction::SwapHand);
+    game.act(PlayerAction::Combine);
+    assert!(game.logged("Lit Cigarette"));
+}
@@ -0,0 +1,162 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{ecs::system::Command, prelude::*};
+use logic_ss_13::{
+    ai::{Behavior, Routine},
+    door::Airlock,
+    hands::Hands,
+    headless::HeadlessGame,
+    interactable::VendingMachine,
+    inventory::Container,
+    replay::PlayerAction,
+    save::{LoadGame, SaveGame, SAVE_VERSION},
+    usuable::Lighter,
+    Npc,
+};
+
+/// Somewhere of its own for each test, they run alongside each other
+fn save_path(name: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!("{}-{}.ron", name, std::process::id()));
+    path.to_string_lossy().to_string()
+}
+
+fn names(game: &HeadlessGame, entities: &[Entity]) -> Vec<String> {
+    entities
+        .iter()
+        .map(|entity| game.world().get::<Name>(*entity).unwrap().to_string())
+        .collect()
+}
+
+fn contents(game: &HeadlessGame, name: &str) -> Vec<String> {
+    let container = game.world().get::<Container>(game.named(name)).unwrap();
+    names(game, &container.contents)
+}
+
+fn stock(game: &HeadlessGame) -> Vec<String> {
+    let machine = game
+        .world()
+        .get::<VendingMachine>(game.named("Vending machine"))
+        .unwrap();
+    names(game, &machine.options)
+}
+
+/// The crate has a box in it as well, this is the one in hand
+fn held_box_contents(game: &HeadlessGame) -> Vec<String> {
+    let held = game
+        .hands()
+        .held()
+        .find_map(|held| game.world().get::<Container>(held))
+        .unwrap();
+    names(game, &held.contents)
+}
+
+/// The vending machine stocks lighters too, only the held one was lit
+fn lit(game: &HeadlessGame) -> bool {
+    game.hands()
+        .held()
+        .filter_map(|held| game.world().get::<Lighter>(held))
+        .any(|lighter| lighter.active)
+}
+
+/// Whoever the first npc's routine says to follow
+fn followed(game: &mut HeadlessGame) -> Option<Entity> {
+    let world = game.world_mut();
+    world
+        .query_filtered::<&Routine, With<Npc>>()
+        .iter(world)
+        .flat_map(|routine| &routine.steps)
+        .find_map(|(behavior, _)| match behavior {
+            Behavior::Follow(target) => Some(*target),
+            _ => None,
+        })
+}
+
+/// A lit lighter in one hand and a box with paper in it in the other
+fn set_up() -> HeadlessGame {
+    let mut game = HeadlessGame::new(0);
+    game.give("lighter");
+    game.act(PlayerAction::Use);
+    game.act(PlayerAction::SwapHand);
+    let paper = game.give("paper");
+    let player = game.player();
+    game.world_mut()
+        .get_mut::<Hands>(player)
+        .unwrap()
+        .release(paper);
+    let holder = game.give("box");
+    game.world_mut()
+        .get_mut::<Container>(holder)
+        .unwrap()
+        .contents
+        .push(paper);
+    game
+}
+
+#[test]
+fn save_round_trips_the_run() {
+    let mut game = set_up();
+    let path = save_path("round-trip");
+    let held = game.held_names();
+    let crate_contents = contents(&game, "Crate");
+    let vending_stock = stock(&game);
+    let location = game.player_location();
+    let clock = game.clock();
+    assert!(lit(&game));
+    assert_eq!(followed(&mut game), Some(game.player()));
+
+    SaveGame(path.clone()).apply(game.world_mut());
+    LoadGame(path.clone()).apply(game.world_mut());
+    game.app.update();
+    fs::remove_file(&path).unwrap();
+
+    assert!(game.logged(&format!("Loaded {}", path)));
+    assert_eq!(game.held_names(), held);
+    assert!(lit(&game));
+    assert_eq!(held_box_contents(&game), vec!["Paper".to_string()]);
+    assert_eq!(contents(&game, "Crate"), crate_contents);
+    assert_eq!(stock(&game), vending_stock);
+    assert_eq!(game.player_location(), location);
+    assert_eq!(game.clock(), clock);
+    // References between entities point at the ones spawned by the load
+    assert_eq!(followed(&mut game), Some(game.player()));
+    let world = game.world_mut();
+    let airlocks = world
+        .query::<(Entity, &Airlock)>()
+        .iter(world)
+        .map(|(entity, airlock)| (entity, airlock.partner))
+        .collect::<Vec<_>>();
+    assert_eq!(airlocks.len(), 2);
+    for (door, partner) in &airlocks {
+        assert!(airlocks.contains(&(*partner, *door)));
+    }
+}
+
+#[test]
+fn older_saves_are_refused() {
+    let mut game = set_up();
+    let path = save_path("older");
+    SaveGame(path.clone()).apply(game.world_mut());
+    let text = fs::read_to_string(&path).unwrap().replacen(
+        &format!("version: {}", SAVE_VERSION),
+        &format!("version: {}", SAVE_VERSION - 1),
+        1,
+    );
+    fs::write(&path, text).unwrap();
+    let player = game.player();
+    let held = game.held_names();
+
+    LoadGame(path.clone()).apply(game.world_mut());
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        game.log().last(),
+        Some(&format!(
+            "Load failed: save is version {}, expected {}",
+            SAVE_VERSION - 1,
+            SAVE_VERSION
+        ))
+    );
+    // Nothing of the running game was thrown away
+    assert_eq!(game.player(), player);
+    assert_eq!(game.held_names(), held);
+}
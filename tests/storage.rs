@@ -0,0 +1,42 @@
+use logic_ss_13::{
+    grid::GridLocation, headless::HeadlessGame, inventory::Container, replay::PlayerAction,
+    DIVIDER_X,
+};
+
+#[test]
+fn locker_turns_away_anyone_without_access() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X + 2, 6));
+    game.act(PlayerAction::Up);
+    assert!(!game.menu_open());
+    assert_eq!(game.log().last().map(String::as_str), Some("Access denied"));
+}
+
+#[test]
+fn locker_opens_for_a_card_in_the_other_hand() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(DIVIDER_X + 2, 6));
+    game.give("engineering_id");
+    game.act(PlayerAction::SwapHand);
+    game.act(PlayerAction::Up);
+    assert!(game.menu_open());
+    game.act(PlayerAction::Confirm);
+    assert!(game.logged("You take the Toolbelt out of the engineering locker"));
+    assert!(game.held_names().contains(&"Toolbelt".to_string()));
+}
+
+#[test]
+fn held_items_go_in_the_crate() {
+    let mut game = HeadlessGame::new(0);
+    game.teleport(GridLocation::new(5, 5));
+    let paper = game.give("paper");
+    game.act(PlayerAction::Right);
+    assert!(game.menu_open());
+    // Past the paper, paper and box already inside
+    game.act_all([PlayerAction::Down; 3]);
+    game.act(PlayerAction::Confirm);
+    let crate_entity = game.named("Crate");
+    let container = game.world().get::<Container>(crate_entity).unwrap();
+    assert_eq!(container.contents.last(), Some(&paper));
+    assert!(game.hands().get_active_held().is_none());
+}
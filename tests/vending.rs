@@ -0,0 +1,26 @@
+use logic_ss_13::{grid::GridLocation, headless::HeadlessGame, replay::PlayerAction, Item};
+
+#[test]
+fn vending_machine_dispenses_the_first_option() {
+    let mut game = HeadlessGame::new(0);
+    game.act_all([PlayerAction::Up; 3]);
+    assert_eq!(game.player_location(), GridLocation::new(1, 4));
+    game.act(PlayerAction::Right);
+    assert!(game.menu_open());
+    game.act(PlayerAction::Confirm);
+    assert_eq!(game.held_names(), vec!["Screwdriver".to_string()]);
+    assert!(game.logged("Dispensed Screwdriver"));
+}
+
+#[test]
+fn dropped_items_can_be_picked_back_up() {
+    let mut game = HeadlessGame::new(0);
+    game.give("screwdriver");
+    let location = game.player_location();
+    game.act(PlayerAction::Drop);
+    assert!(game.hands().get_active_held().is_none());
+    assert!(game.grid::<Item>().occupied(&location));
+    game.act(PlayerAction::Pickup);
+    assert_eq!(game.held_names(), vec!["Screwdriver".to_string()]);
+    assert!(!game.grid::<Item>().occupied(&location));
+}
@@ -0,0 +1,50 @@
+use bevy::{prelude::*, utils::HashMap};
+use logic_ss_13::{
+    atmos::{Atmosphere, ONE_ATMOSPHERE},
+    grid::{GridLocation, DEFAULT_GRID_SIZE},
+    headless::HeadlessGame,
+    turn::Energy,
+    Floor, Npc,
+};
+
+fn npcs(game: &mut HeadlessGame) -> HashMap<Entity, (GridLocation, u64)> {
+    let world = game.world_mut();
+    world
+        .query_filtered::<(Entity, &GridLocation, &Energy), With<Npc>>()
+        .iter(world)
+        .map(|(npc, location, energy)| (npc, (location.clone(), energy.ready_at)))
+        .collect()
+}
+
+#[test]
+fn starting_level_fills_the_default_grid() {
+    let game = HeadlessGame::new(0);
+    assert_eq!(game.grid::<Floor>().size(), DEFAULT_GRID_SIZE);
+}
+
+#[test]
+fn npcs_act_while_the_player_waits() {
+    let mut game = HeadlessGame::new(0);
+    let before = npcs(&mut game);
+    assert!(!before.is_empty());
+    game.wait_turns(50);
+    let after = npcs(&mut game);
+    // Every npc has taken turns and at least one has wandered off
+    assert!(before
+        .iter()
+        .all(|(npc, (_, ready_at))| after[npc].1 > *ready_at));
+    assert!(before
+        .iter()
+        .any(|(npc, (location, _))| after[npc].0 != *location));
+}
+
+#[test]
+fn walls_keep_the_air_in() {
+    let mut game = HeadlessGame::new(0);
+    game.wait_turns(50);
+    // A little is lost to smoke and breathing but nobody suffocates
+    let location = game.player_location();
+    let air = game.world().resource::<Atmosphere>().get(&location);
+    assert!(air.pressure() > ONE_ATMOSPHERE * 0.9);
+    assert!(!game.log().iter().any(|entry| entry.starts_with("You gasp")));
+}